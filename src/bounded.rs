@@ -0,0 +1,117 @@
+/*
+    Bounded mode
+
+    A regular tree grows for as long as keys keep coming.  BoundedTree caps it at a
+    maximum number of leaves, and every append checks the cap before touching the
+    tree, so running out of room is an error the caller can handle instead of
+    unbounded memory growth.
+
+    Read-only tree methods (root, proof, len, ...) are available through Deref.
+*/
+
+use core::ops::Deref;
+
+use sha2::Sha256;
+use sha2::digest::Digest;
+
+use crate::error::MerkleError;
+use crate::hashing::{Hash, Key};
+use crate::key::MerkleKey;
+use crate::tree::MerkleTree;
+
+/// An append-only Merkle tree that refuses to grow past a maximum number of leaves.
+pub struct BoundedTree<D: Digest = Sha256, const N: usize = 32> {
+    tree: MerkleTree<D, N>,
+    max_leaves: usize,
+}
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Creates an empty tree that holds at most `n` leaves.
+    pub fn with_max_leaves(n: usize) -> BoundedTree<D, N> {
+        BoundedTree {
+            tree: MerkleTree::new(),
+            max_leaves: n,
+        }
+    }
+}
+
+impl<D: Digest, const N: usize> BoundedTree<D, N> {
+    /// Appends `key` like `MerkleTree::append` and returns its leaf index, or
+    /// MerkleError::Full if the tree already holds `max_leaves()` leaves.
+    /// The tree is unchanged on error.
+    pub fn append(&mut self, key: Key) -> Result<usize, MerkleError> {
+        self.append_key(key)
+    }
+
+    /// Same as `append`, for a key of any `MerkleKey` type like `MerkleTree::append_key`.
+    pub fn append_key<K: MerkleKey>(&mut self, key: K) -> Result<usize, MerkleError> {
+        self.check_room(1)?;
+        self.tree.append_key(key);
+        Ok(self.tree.len() - 1)
+    }
+
+    /// Same as `append`, with an already-computed leaf hash.
+    pub fn append_hash(&mut self, leaf: Hash<N>) -> Result<usize, MerkleError> {
+        self.check_room(1)?;
+        self.tree.append_hash(leaf);
+        Ok(self.tree.len() - 1)
+    }
+
+    /// Appends all of `keys` with a single rebuild, or none of them if they don't all fit.
+    pub fn append_many(&mut self, keys: &[Key]) -> Result<(), MerkleError> {
+        self.check_room(keys.len())?;
+        self.tree.append_many(keys);
+        Ok(())
+    }
+
+    /// Returns the maximum number of leaves.
+    pub fn max_leaves(&self) -> usize {
+        self.max_leaves
+    }
+
+    /// Unwraps the underlying tree, dropping the limit.
+    pub fn into_tree(self) -> MerkleTree<D, N> {
+        self.tree
+    }
+
+    /// Fails unless `additional` more leaves fit under the limit.
+    fn check_room(&self, additional: usize) -> Result<(), MerkleError> {
+        let room = self.max_leaves - self.tree.len();
+        if additional > room {
+            return Err(MerkleError::Full);
+        }
+        Ok(())
+    }
+}
+
+impl<D: Digest, const N: usize> Deref for BoundedTree<D, N> {
+    type Target = MerkleTree<D, N>;
+
+    fn deref(&self) -> &MerkleTree<D, N> {
+        &self.tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::MerkleError;
+    use crate::tree::Sha256Tree;
+
+    #[test]
+    fn append_past_the_limit_is_full() {
+        let mut bounded = Sha256Tree::with_max_leaves(3);
+        assert_eq!(bounded.append_many(&[0, 1, 2, 3]), Err(MerkleError::Full));
+        assert!(bounded.is_empty());
+
+        for key in 0..3u64 {
+            assert_eq!(bounded.append(key), Ok(key as usize));
+        }
+        let root = bounded.root();
+        assert_eq!(bounded.append(3u64), Err(MerkleError::Full));
+        assert_eq!(bounded.append_hash([0; 32]), Err(MerkleError::Full));
+
+        // The failed appends left the tree as it was
+        assert_eq!(bounded.len(), 3);
+        assert_eq!(bounded.root(), root);
+    }
+}
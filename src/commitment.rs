@@ -0,0 +1,121 @@
+/*
+    Root commitments
+
+    A bare root says nothing about the tree it came from.  RootCommitment publishes it
+    together with the tree size and a format version, so a verifier can't be handed a
+    root of a different-size tree, and a later format can't be mistaken for this one.
+
+    Byte layout (integers big-endian):
+        - version:   u8, currently 1
+        - hash size: u32, bytes per hash (32 for SHA-256)
+        - tree size: u64, number of leaves
+        - root:      hash size raw bytes, all zeros for an empty tree
+
+    from_bytes rejects any other version or hash size instead of guessing.
+*/
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use sha2::digest::Digest;
+
+use crate::error::CommitmentError;
+use crate::hashing::{Hash, hash_to_hex};
+use crate::tree::MerkleTree;
+
+/// Format version written by `RootCommitment::to_bytes`.
+pub const COMMITMENT_VERSION: u8 = 1;
+
+/// Size of the version, hash size and tree size fields in front of the root.
+const HEADER_LEN: usize = 1 + 4 + 8;
+
+/// A root bound to the size of its tree and a format version, for publishing.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct RootCommitment<const N: usize = 32> {
+    /// Format version, COMMITMENT_VERSION for commitments made by this crate.
+    pub version: u8,
+    /// Number of leaves in the committed tree.
+    pub tree_size: u64,
+    /// Root of that tree, all zeros if it is empty.
+    pub root: Hash<N>,
+}
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Returns a commitment to the current root and size.
+    pub fn commitment(&self) -> RootCommitment<N> {
+        RootCommitment::new(self.len() as u64, self.root_or_empty())
+    }
+}
+
+impl<const N: usize> RootCommitment<N> {
+    /// Commits to `root` of a tree of `tree_size` leaves, in the current format version.
+    pub fn new(tree_size: u64, root: Hash<N>) -> Self {
+        RootCommitment {
+            version: COMMITMENT_VERSION,
+            tree_size,
+            root,
+        }
+    }
+
+    /// Encodes the version, hash size, tree size and root.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + N);
+
+        bytes.push(self.version);
+        bytes.extend_from_slice(&(N as u32).to_be_bytes());
+        bytes.extend_from_slice(&self.tree_size.to_be_bytes());
+        bytes.extend_from_slice(&self.root);
+
+        bytes
+    }
+
+    /// Decodes bytes written by `to_bytes`.  An unknown version, a hash size other than N,
+    /// or a wrong length is reported as an error, never a panic.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CommitmentError> {
+        // Check the header first, so a commitment in another format says so
+        // instead of looking like a length error
+        let expected = HEADER_LEN + N;
+        let truncated = CommitmentError::Truncated { expected, found: bytes.len() };
+
+        let Some((&version, rest)) = bytes.split_first() else {
+            return Err(truncated);
+        };
+        if version != COMMITMENT_VERSION {
+            return Err(CommitmentError::UnsupportedVersion(version));
+        }
+
+        let Some((hash_size, rest)) = rest.split_first_chunk::<4>() else {
+            return Err(truncated);
+        };
+        let hash_size = u32::from_be_bytes(*hash_size);
+        if hash_size as usize != N {
+            return Err(CommitmentError::HashSizeMismatch { expected: N, found: hash_size });
+        }
+
+        if bytes.len() < expected {
+            return Err(truncated);
+        }
+        if bytes.len() > expected {
+            return Err(CommitmentError::TrailingBytes { expected, found: bytes.len() });
+        }
+
+        let (tree_size, rest) = rest.split_first_chunk::<8>().expect("length was checked above");
+        let root = *rest.first_chunk::<N>().expect("length was checked above");
+
+        Ok(RootCommitment {
+            version,
+            tree_size: u64::from_be_bytes(*tree_size),
+            root,
+        })
+    }
+}
+
+impl<const N: usize> fmt::Debug for RootCommitment<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RootCommitment")
+            .field("version", &self.version)
+            .field("tree_size", &self.tree_size)
+            .field("root", &hash_to_hex(&self.root))
+            .finish()
+    }
+}
@@ -0,0 +1,453 @@
+/*
+    Consistency proofs
+
+    Since the tree is append-only, the tree of size m is a prefix of the tree of size n >= m.
+    A consistency proof lets someone holding only the old root check this against the new root.
+
+    The old tree is fully described by its "peaks": the perfect subtrees covering [0, m),
+    one per set bit of m, largest (leftmost) first.  For example m = 6 has peaks over
+    leaves [0, 4) and [4, 6).  These nodes are identical in both trees.
+
+    Proof layout:
+        - the peaks of the old tree, left to right
+        - then every node of the new tree needed to rebuild the new root from those peaks,
+          in the order met by a depth-first, left-to-right walk from the new root.
+
+    The walk only descends into nodes covering both old and new leaves.  Nodes entirely
+    inside [0, m) are peaks, nodes entirely past m are taken from the proof.
+
+    The verifier rebuilds the old root from the peaks (handling lone nodes with the same
+    OddNodePolicy as the tree) and the new root from the peaks plus the remaining hashes.
+
+    Single appends:
+        - when the tree grew by exactly one known key, the only new node outside the old
+          peaks is that key's leaf, so the proof is just the old peaks (append_proof)
+        - verify_append merges the new leaf into the peaks it completes, smallest first,
+          and rebuilds both roots without walking down from the new root
+
+    Snapshots:
+        - a Snapshot keeps only a size and the root at that size, instead of a whole clone
+        - verify_snapshot later checks a consistency proof from the snapshot to the current tree
+*/
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use sha2::Sha256;
+use sha2::digest::Digest;
+
+use crate::hashing::{Hash, Key, ct_eq, hash_internal_with, hash_key_with, hash_to_hex};
+use crate::tree::{MerkleTree, OddNodePolicy, height_for, level_width};
+
+/// The size and root of a tree at one point in time, as returned by `MerkleTree::snapshot`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot<const N: usize = 32> {
+    /// Number of leaves when the snapshot was taken.
+    pub size: usize,
+    /// Root at that size, all zeros for an empty tree.
+    pub root: Hash<N>,
+}
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Captures the current size and root, to later prove the tree only grew since.
+    pub fn snapshot(&self) -> Snapshot<N> {
+        Snapshot {
+            size: self.len(),
+            root: self.root_or_empty(),
+        }
+    }
+
+    /// Checks that this tree extends the one `snap` was taken from, using
+    /// `consistency_proof(snap.size)`.  An empty snapshot is extended by any tree
+    /// and needs an empty proof.
+    pub fn verify_snapshot(&self, snap: &Snapshot<N>, proof: &[Hash<N>]) -> bool {
+        if snap.size == 0 {
+            return proof.is_empty() && ct_eq(&snap.root, &[0u8; N]);
+        }
+
+        let Some(root) = self.root() else {
+            return false;
+        };
+        verify_consistency_by(
+            self.policy,
+            snap.root,
+            root,
+            snap.size,
+            self.len(),
+            proof,
+            |left, right| self.hash_node(left, right),
+        )
+    }
+
+    /// Returns a proof that the tree of `old_size` leaves is a prefix of this tree,
+    /// or None if `old_size` is 0 or larger than the current tree.
+    pub fn consistency_proof(&self, old_size: usize) -> Option<Vec<Hash<N>>> {
+        let new_size = self.len();
+        if old_size == 0 || old_size > new_size {
+            return None;
+        }
+
+        // Peaks of the old tree come first
+        let mut proof: Vec<Hash<N>> = peaks(old_size)
+            .map(|(level, index)| self.levels[level][index])
+            .collect();
+
+        // Then the nodes needed to rebuild the new root, starting at the root
+        let top = height_for(new_size) - 1;
+        self.collect_new_nodes(top, 0, old_size, new_size, &mut proof);
+
+        Some(proof)
+    }
+
+    /// Returns a proof that the last append extended the tree before it: the peaks of the
+    /// tree without its last leaf.  None if the tree has fewer than two leaves.
+    pub fn append_proof(&self) -> Option<Vec<Hash<N>>> {
+        let old_size = self.len().checked_sub(1).filter(|&size| size > 0)?;
+
+        Some(peaks(old_size).map(|(level, index)| self.levels[level][index]).collect())
+    }
+
+    /// Returns the root the tree had when it held exactly `size` leaves, without changing it.
+    /// Returns None if `size` is 0 or larger than the current tree.
+    ///
+    /// The perfect subtrees (peaks) of that older tree are already stored in the levels,
+    /// so only its right edge is rehashed: O(log n) instead of a rebuild over the leaves.
+    pub fn root_at(&self, size: usize) -> Option<Hash<N>> {
+        if size == 0 || size > self.len() {
+            return None;
+        }
+
+        let old_peaks: Vec<Hash<N>> = peaks(size)
+            .map(|(level, index)| self.levels[level][index])
+            .collect();
+
+        root_from_peaks(self.policy, &old_peaks, size, |left, right| self.hash_node(left, right))
+    }
+
+    /// Returns the smallest leaf index where this tree and `other` differ,
+    /// or None if one is a prefix of the other.
+    ///
+    /// Both trees share the peaks of their common prefix size, so only those are compared
+    /// and the first mismatching one is descended into: O(log n) comparisons.
+    pub fn first_divergence(&self, other: &MerkleTree<D, N>) -> Option<usize> {
+        let common = self.len().min(other.len());
+
+        // Find the leftmost peak of the common prefix that differs
+        let (mut level, mut index) = peaks(common)
+            .find(|&(level, index)| self.levels[level][index] != other.levels[level][index])?;
+
+        // Walk down, going left whenever the left child already differs
+        while level > 0 {
+            level -= 1;
+            index *= 2;
+            if self.levels[level][index] == other.levels[level][index] {
+                index += 1;
+            }
+        }
+
+        Some(index)
+    }
+
+    /// Pushes the new-tree nodes under (level, index) that a verifier can't derive from the peaks.
+    fn collect_new_nodes(
+        &self,
+        level: usize,
+        index: usize,
+        old_size: usize,
+        new_size: usize,
+        proof: &mut Vec<Hash<N>>,
+    ) {
+        let (start, end) = node_range(level, index);
+
+        // Entirely inside the old tree: this is a peak, already in the proof
+        if end <= old_size {
+            return;
+        }
+
+        // Entirely made of new leaves: the verifier needs it as-is
+        if start >= old_size {
+            proof.push(self.levels[level][index]);
+            return;
+        }
+
+        // Covers both old and new leaves: descend into both children.
+        // A single leaf can't straddle old_size, so level > 0 here.
+        self.collect_new_nodes(level - 1, 2 * index, old_size, new_size, proof);
+
+        // A missing right child is derived from the left one by the policy, nothing to add
+        if 2 * index + 1 < level_width(new_size, level - 1) {
+            self.collect_new_nodes(level - 1, 2 * index + 1, old_size, new_size, proof);
+        }
+    }
+}
+
+/// Checks that the tree with `old_root` and `old_size` leaves is a prefix of the tree
+/// with `new_root` and `new_size` leaves, using a proof from `consistency_proof`.
+/// Expects a default SHA-256 tree with duplicated odd nodes.
+pub fn verify_consistency(
+    old_root: Hash,
+    new_root: Hash,
+    old_size: usize,
+    new_size: usize,
+    proof: &[Hash],
+) -> bool {
+    verify_consistency_with::<Sha256, 32>(
+        OddNodePolicy::Duplicate,
+        old_root,
+        new_root,
+        old_size,
+        new_size,
+        proof,
+    )
+}
+
+/// Same as `verify_consistency`, for a tree hashed with the digest `D` using `policy`.
+pub fn verify_consistency_with<D: Digest, const N: usize>(
+    policy: OddNodePolicy,
+    old_root: Hash<N>,
+    new_root: Hash<N>,
+    old_size: usize,
+    new_size: usize,
+    proof: &[Hash<N>],
+) -> bool {
+    verify_consistency_by(
+        policy,
+        old_root,
+        new_root,
+        old_size,
+        new_size,
+        proof,
+        hash_internal_with::<D, N>,
+    )
+}
+
+/// Same as `verify_consistency_with`, hashing internal nodes with `hash_node`,
+/// e.g. a tree's runtime hasher.
+pub(crate) fn verify_consistency_by<const N: usize>(
+    policy: OddNodePolicy,
+    old_root: Hash<N>,
+    new_root: Hash<N>,
+    old_size: usize,
+    new_size: usize,
+    proof: &[Hash<N>],
+    hash_node: impl Fn(Hash<N>, Hash<N>) -> Hash<N>,
+) -> bool {
+    if old_size == 0 || old_size > new_size {
+        return false;
+    }
+
+    // Split off the peaks of the old tree
+    let peak_count = old_size.count_ones() as usize;
+    if proof.len() < peak_count {
+        return false;
+    }
+    let (old_peaks, rest) = proof.split_at(peak_count);
+
+    // The peaks must rebuild the old root
+    let old_computed = root_from_peaks(policy, old_peaks, old_size, &hash_node);
+    if !old_computed.is_some_and(|root| ct_eq(&root, &old_root)) {
+        return false;
+    }
+
+    // The peaks plus the remaining hashes must rebuild the new root, using every hash exactly once
+    let mut rest = rest.iter();
+    let top = height_for(new_size) - 1;
+    let new_computed =
+        rebuild_new_node(policy, top, 0, old_size, new_size, old_peaks, &mut rest, &hash_node);
+
+    rest.next().is_none() && new_computed.is_some_and(|root| ct_eq(&root, &new_root))
+}
+
+/// Checks that appending `new_key` to the tree with `old_root` and `old_size` leaves gives
+/// `new_root`, using a proof from `append_proof`.  Cheaper than `verify_consistency` for a
+/// tree that grew by one leaf.  Expects a default SHA-256 tree with duplicated odd nodes.
+pub fn verify_append(
+    old_root: Hash,
+    old_size: usize,
+    new_key: Key,
+    new_root: Hash,
+    proof: &[Hash],
+) -> bool {
+    verify_append_with::<Sha256, 32>(
+        OddNodePolicy::Duplicate,
+        old_root,
+        old_size,
+        new_key,
+        new_root,
+        proof,
+    )
+}
+
+/// Same as `verify_append`, for a tree hashed with the digest `D` using `policy`.
+pub fn verify_append_with<D: Digest, const N: usize>(
+    policy: OddNodePolicy,
+    old_root: Hash<N>,
+    old_size: usize,
+    new_key: Key,
+    new_root: Hash<N>,
+    proof: &[Hash<N>],
+) -> bool {
+    let Some(new_size) = old_size.checked_add(1) else {
+        return false;
+    };
+
+    // The peaks must rebuild the old root
+    let old_computed = root_from_peaks(policy, proof, old_size, hash_internal_with::<D, N>);
+    if !old_computed.is_some_and(|root| ct_eq(&root, &old_root)) {
+        return false;
+    }
+
+    // The new leaf merges with one peak per trailing set bit of old_size, smallest first
+    let mut remaining = proof.len();
+    let mut merged = hash_key_with::<D, N>(new_key);
+    for _ in 0..old_size.trailing_ones() {
+        remaining -= 1;
+        merged = hash_internal_with::<D, N>(proof[remaining], merged);
+    }
+
+    let mut new_peaks = proof[..remaining].to_vec();
+    new_peaks.push(merged);
+
+    root_from_peaks(policy, &new_peaks, new_size, hash_internal_with::<D, N>)
+        .is_some_and(|root| ct_eq(&root, &new_root))
+}
+
+/// Mirrors `collect_new_nodes`, computing the hash of (level, index) in the new tree.
+#[allow(clippy::too_many_arguments)]
+fn rebuild_new_node<'a, const N: usize>(
+    policy: OddNodePolicy,
+    level: usize,
+    index: usize,
+    old_size: usize,
+    new_size: usize,
+    old_peaks: &[Hash<N>],
+    rest: &mut impl Iterator<Item = &'a Hash<N>>,
+    hash_node: &impl Fn(Hash<N>, Hash<N>) -> Hash<N>,
+) -> Option<Hash<N>> {
+    let (start, end) = node_range(level, index);
+
+    // A peak of the old tree: peaks are ordered by decreasing level, one per set bit
+    if end <= old_size {
+        let higher_peaks = old_size.checked_shr(level as u32 + 1).unwrap_or(0).count_ones();
+        return old_peaks.get(higher_peaks as usize).cloned();
+    }
+
+    // Made of new leaves only: next hash from the proof
+    if start >= old_size {
+        return rest.next().cloned();
+    }
+
+    let mut child = |index| {
+        rebuild_new_node(policy, level - 1, index, old_size, new_size, old_peaks, rest, hash_node)
+    };
+
+    let left = child(2 * index)?;
+    if 2 * index + 1 >= level_width(new_size, level - 1) {
+        return Some(policy.lone_parent_by(&left, hash_node));
+    }
+    let right = child(2 * index + 1)?;
+
+    Some(hash_node(left, right))
+}
+
+/// Rebuilds the root of a tree of `size` leaves from its peaks (left to right),
+/// handling lone nodes on the right edge with `policy` exactly like append does.
+/// Internal nodes are hashed with `hash_node`.
+pub(crate) fn root_from_peaks<const N: usize>(
+    policy: OddNodePolicy,
+    peaks: &[Hash<N>],
+    size: usize,
+    hash_node: impl Fn(Hash<N>, Hash<N>) -> Hash<N>,
+) -> Option<Hash<N>> {
+    if size == 0 || peaks.len() != size.count_ones() as usize {
+        return None;
+    }
+
+    // Start from the smallest peak, the right edge at its level
+    let mut remaining = peaks.len() - 1;
+    let mut acc = peaks[remaining];
+    let mut width = size >> size.trailing_zeros();
+
+    // Walk up the right edge until a level has a single node
+    while width > 1 {
+        if width.is_multiple_of(2) {
+            // The right-edge node is a right child: its sibling is the next peak to the left
+            remaining -= 1;
+            acc = hash_node(peaks[remaining], acc);
+        } else {
+            // The right-edge node is alone
+            acc = policy.lone_parent_by(&acc, &hash_node);
+        }
+        width = width.div_ceil(2);
+    }
+
+    Some(acc)
+}
+
+/// The (level, index) of every peak of a tree with `size` leaves, left to right.
+pub(crate) fn peaks(size: usize) -> impl Iterator<Item = (usize, usize)> {
+    (0..usize::BITS as usize)
+        .rev()
+        .filter(move |&level| size >> level & 1 == 1)
+        .map(move |level| (level, (size >> level) - 1))
+}
+
+/// The half-open leaf range [start, end) covered by node (level, index).
+fn node_range(level: usize, index: usize) -> (usize, usize) {
+    let start = index.checked_shl(level as u32).unwrap_or(usize::MAX);
+    let end = (index + 1).checked_shl(level as u32).unwrap_or(usize::MAX);
+    (start, end)
+}
+
+impl<const N: usize> fmt::Debug for Snapshot<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Snapshot")
+            .field("size", &self.size)
+            .field("root", &hash_to_hex(&self.root))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::{LeafHasher, NodeHasher};
+    use crate::hashing::hash_leaf_bytes_with;
+    use crate::tree::Sha256Tree;
+    use sha2::Sha512;
+
+    fn check_snapshots(mut tree: Sha256Tree) {
+        let mut snaps = alloc::vec![tree.snapshot()];
+        for key in 0..20u64 {
+            tree.append(key);
+            snaps.push(tree.snapshot());
+
+            for snap in &snaps {
+                let proof = if snap.size == 0 {
+                    Vec::new()
+                } else {
+                    tree.consistency_proof(snap.size).unwrap()
+                };
+                assert!(tree.verify_snapshot(snap, &proof), "{} from {}", tree.len(), snap.size);
+            }
+        }
+    }
+
+    #[test]
+    fn verify_snapshot_with_digest() {
+        check_snapshots(Sha256Tree::new());
+        check_snapshots(Sha256Tree::rfc6962());
+    }
+
+    #[test]
+    fn verify_snapshot_with_runtime_hasher() {
+        check_snapshots(Sha256Tree::with_hasher(
+            hash_leaf_bytes_with::<Sha512, 32>,
+            hash_internal_with::<Sha512, 32>,
+        ));
+        check_snapshots(Sha256Tree::with_hashers(
+            LeafHasher::default(),
+            NodeHasher::from_digest::<Sha512>(),
+        ));
+    }
+}
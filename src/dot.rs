@@ -0,0 +1,71 @@
+/*
+    Graphviz export
+
+    Each node is named n<level>_<index> and labeled with the first 8 hex characters
+    of its hash (all of them for hashes under 4 bytes).  Edges point from parents to
+    children.  A lone node that was duplicated gets a second, dashed edge to
+    itself-as-sibling; a promoted node gets a single dotted edge.
+
+    Render with: dot -Tpng tree.dot -o tree.png
+*/
+
+use alloc::format;
+use alloc::string::String;
+use sha2::digest::Digest;
+
+use crate::hashing::hash_to_hex;
+use crate::tree::{MerkleTree, OddNodePolicy};
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Returns a Graphviz DOT description of the whole tree.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph MerkleTree {\n");
+        dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+
+        // One labeled node per hash, leaves first
+        for (level, nodes) in self.levels.iter().enumerate() {
+            for (index, hash) in nodes.iter().enumerate() {
+                // Hashes narrower than 4 bytes are shown whole
+                let hex = hash_to_hex(hash);
+                let label = &hex[..hex.len().min(8)];
+                dot.push_str(&format!("    n{level}_{index} [label=\"{label}\"];\n"));
+            }
+        }
+
+        // Edges from each parent to the children it was built from
+        for (level, nodes) in self.levels.iter().enumerate().skip(1) {
+            let below = self.levels[level - 1].len();
+
+            for index in 0..nodes.len() {
+                let left = 2 * index;
+                let right = left + 1;
+
+                if right < below {
+                    dot.push_str(&format!("    n{level}_{index} -> n{}_{left};\n", level - 1));
+                    dot.push_str(&format!("    n{level}_{index} -> n{}_{right};\n", level - 1));
+                    continue;
+                }
+
+                // Lone child, show how the odd-node policy handled it
+                match self.policy {
+                    OddNodePolicy::Duplicate => {
+                        dot.push_str(&format!("    n{level}_{index} -> n{}_{left};\n", level - 1));
+                        dot.push_str(&format!(
+                            "    n{level}_{index} -> n{}_{left} [style=dashed, label=\"dup\"];\n",
+                            level - 1
+                        ));
+                    }
+                    OddNodePolicy::Promote => {
+                        dot.push_str(&format!(
+                            "    n{level}_{index} -> n{}_{left} [style=dotted, label=\"promoted\"];\n",
+                            level - 1
+                        ));
+                    }
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
@@ -0,0 +1,164 @@
+use core::fmt;
+
+/// Errors returned by fallible tree operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MerkleError {
+    /// The tree has no leaves, so there is no root.
+    Empty,
+    /// The key was already inserted into a set-mode tree.
+    DuplicateKey(u64),
+    /// The leaf index is not below the number of leaves.
+    IndexOutOfRange { index: usize, len: usize },
+    /// The key is not larger than the last key of a sorted-mode tree.
+    UnsortedKey { key: u64, last: u64 },
+    /// A bounded or fixed-depth tree already holds its maximum number of leaves.
+    Full,
+    /// The tree doesn't have the number of levels its leaf count implies.
+    InvalidHeight { expected: usize, found: usize },
+    /// A level doesn't hold half (rounded up) the nodes of the level below it.
+    InvalidLevelWidth { level: usize, expected: usize, found: usize },
+    /// A stored node doesn't match the hash of its children.
+    NodeMismatch { level: usize, index: usize },
+}
+
+impl fmt::Display for MerkleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MerkleError::Empty => write!(f, "merkle tree is empty"),
+            MerkleError::DuplicateKey(key) => write!(f, "key {} is already in the tree", key),
+            MerkleError::IndexOutOfRange { index, len } => write!(
+                f,
+                "leaf index {} is out of range for a tree of {} leaves",
+                index, len
+            ),
+            MerkleError::UnsortedKey { key, last } => write!(
+                f,
+                "key {} is not larger than the last key {}",
+                key, last
+            ),
+            MerkleError::Full => write!(f, "the tree holds its maximum number of leaves"),
+            MerkleError::InvalidHeight { expected, found } => write!(
+                f,
+                "tree should have {} levels, found {}",
+                expected, found
+            ),
+            MerkleError::InvalidLevelWidth { level, expected, found } => write!(
+                f,
+                "level {} should have {} nodes, found {}",
+                level, expected, found
+            ),
+            MerkleError::NodeMismatch { level, index } => write!(
+                f,
+                "node {} at level {} does not match the hash of its children",
+                index, level
+            ),
+        }
+    }
+}
+
+impl core::error::Error for MerkleError {}
+
+/// Errors returned when parsing a hash from a hex string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HexError {
+    /// The string does not have exactly two hex characters per hash byte.
+    InvalidLength { expected: usize, found: usize },
+    /// A character at the given position is not a hex digit.
+    InvalidChar { index: usize, found: char },
+}
+
+impl fmt::Display for HexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HexError::InvalidLength { expected, found } => write!(
+                f,
+                "expected {} hex characters, found {}",
+                expected, found
+            ),
+            HexError::InvalidChar { index, found } => write!(
+                f,
+                "invalid hex character {:?} at position {}",
+                found, index
+            ),
+        }
+    }
+}
+
+impl core::error::Error for HexError {}
+
+/// Errors returned when decoding a proof from bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProofError {
+    /// The input ended before the length prefix or a step was complete.
+    Truncated { expected: usize, found: usize },
+    /// The input has bytes left over after the last step.
+    TrailingBytes { expected: usize, found: usize },
+    /// A direction byte was neither 0 (left) nor 1 (right).
+    InvalidDirection { step: usize, found: u8 },
+}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofError::Truncated { expected, found } => write!(
+                f,
+                "proof truncated: expected {} bytes, found {}",
+                expected, found
+            ),
+            ProofError::TrailingBytes { expected, found } => write!(
+                f,
+                "proof has trailing bytes: expected {} bytes, found {}",
+                expected, found
+            ),
+            ProofError::InvalidDirection { step, found } => write!(
+                f,
+                "invalid direction byte {:#04x} at step {}",
+                found, step
+            ),
+        }
+    }
+}
+
+impl core::error::Error for ProofError {}
+
+/// Errors returned when decoding a root commitment from bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommitmentError {
+    /// The input is shorter than the header and root.
+    Truncated { expected: usize, found: usize },
+    /// The input has bytes left over after the root.
+    TrailingBytes { expected: usize, found: usize },
+    /// The version byte is not one this crate can read.
+    UnsupportedVersion(u8),
+    /// The hash size field doesn't match the expected hash size.
+    HashSizeMismatch { expected: usize, found: u32 },
+}
+
+impl fmt::Display for CommitmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommitmentError::Truncated { expected, found } => write!(
+                f,
+                "commitment truncated: expected {} bytes, found {}",
+                expected, found
+            ),
+            CommitmentError::TrailingBytes { expected, found } => write!(
+                f,
+                "commitment has trailing bytes: expected {} bytes, found {}",
+                expected, found
+            ),
+            CommitmentError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported commitment version {}",
+                version
+            ),
+            CommitmentError::HashSizeMismatch { expected, found } => write!(
+                f,
+                "commitment hash size is {} bytes, expected {}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl core::error::Error for CommitmentError {}
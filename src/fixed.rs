@@ -0,0 +1,218 @@
+/*
+    Fixed-depth mode
+
+    A regular tree's proofs get longer as it grows, and lone nodes on odd levels make some
+    of them a step shorter (when promoting) than others.  FixedDepthTree instead has all
+    2^depth leaf slots from the start, each holding the empty leaf (all zeros) until it is
+    filled, so the tree is always perfect and every proof has exactly `depth` steps.
+
+    Only the nodes above filled slots are stored.  An empty subtree has the same hash
+    everywhere on its level, computed once per tree, like in SparseMerkleTree:
+        - empty[0]     = all zeros (an empty leaf)
+        - empty[l + 1] = H(0x01 || empty[l] || empty[l])
+    Slots fill left to right, so level l stores ceil(filled / 2^l) nodes, and any node
+    past those is empty[l].  Creating a tree hashes depth nodes, whatever 2^depth is.
+
+    append writes the next empty slot and recomputes its path to the root, O(depth).
+*/
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use sha2::Sha256;
+use sha2::digest::Digest;
+
+use crate::error::MerkleError;
+use crate::hashing::{Hash, Key, hash_internal_with, hash_leaf_bytes_with};
+use crate::key::MerkleKey;
+use crate::tree::MerkleTree;
+
+/// A perfect Merkle tree of 2^depth slots, filled left to right.
+pub struct FixedDepthTree<D: Digest = Sha256, const N: usize = 32> {
+    /// Nodes covering at least one filled slot, by level, leaves first.
+    levels: Vec<Vec<Hash<N>>>,
+    /// Hash of an empty subtree at each level, leaves first, root level last.
+    empty: Vec<Hash<N>>,
+    depth: u32,
+    digest: PhantomData<D>,
+}
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Creates a tree of exactly 2^`depth` empty leaf slots, so every proof has `depth` steps.
+    /// Panics if 2^`depth` slots don't fit in a usize.
+    pub fn fixed_depth(depth: u32) -> FixedDepthTree<D, N> {
+        assert!(
+            1usize.checked_shl(depth).is_some(),
+            "fixed_depth: 2^depth slots must fit in a usize"
+        );
+
+        let mut empty = Vec::with_capacity(depth as usize + 1);
+        empty.push([0u8; N]);
+        for level in 0..depth as usize {
+            empty.push(hash_internal_with::<D, N>(empty[level], empty[level]));
+        }
+
+        FixedDepthTree {
+            levels: vec![Vec::new(); depth as usize + 1],
+            empty,
+            depth,
+            digest: PhantomData,
+        }
+    }
+}
+
+impl<D: Digest, const N: usize> FixedDepthTree<D, N> {
+    /// Writes the hash of `key` into the next empty slot and returns its index,
+    /// or MerkleError::Full if every slot is taken.  The tree is unchanged on error.
+    pub fn append(&mut self, key: Key) -> Result<usize, MerkleError> {
+        self.append_key(key)
+    }
+
+    /// Same as `append`, for a key of any `MerkleKey` type like `MerkleTree::append_key`.
+    pub fn append_key<K: MerkleKey>(&mut self, key: K) -> Result<usize, MerkleError> {
+        if self.is_full() {
+            return Err(MerkleError::Full);
+        }
+        self.append_hash(hash_leaf_bytes_with::<D, N>(&key.to_leaf_bytes()))
+    }
+
+    /// Same as `append`, with an already-computed leaf hash.
+    pub fn append_hash(&mut self, leaf: Hash<N>) -> Result<usize, MerkleError> {
+        if self.is_full() {
+            return Err(MerkleError::Full);
+        }
+
+        let index = self.filled();
+        self.levels[0].push(leaf);
+
+        // Recompute the path up to the root; its node on each level is new or the last one
+        for level in 1..self.levels.len() {
+            let node_index = index >> level;
+            let left = self.node(level - 1, 2 * node_index);
+            let right = self.node(level - 1, 2 * node_index + 1);
+            let parent = hash_internal_with::<D, N>(left, right);
+
+            let nodes = &mut self.levels[level];
+            if node_index < nodes.len() {
+                nodes[node_index] = parent;
+            } else {
+                nodes.push(parent);
+            }
+        }
+
+        Ok(index)
+    }
+
+    /// Returns the root hash.  A tree with no filled slots has the all-empty root.
+    pub fn root(&self) -> Hash<N> {
+        self.node(self.depth as usize, 0)
+    }
+
+    /// Returns the proof for the slot at `index`, filled or not, as `depth` steps in the
+    /// format of `MerkleTree::proof`, or None if `index` is out of range.
+    pub fn proof(&self, index: usize) -> Option<Vec<(Hash<N>, bool)>> {
+        if index >= self.capacity() {
+            return None;
+        }
+
+        let steps = (0..self.depth as usize)
+            .map(|level| {
+                let node_index = index >> level;
+                (self.node(level, node_index ^ 1), node_index.is_multiple_of(2))
+            })
+            .collect();
+        Some(steps)
+    }
+
+    /// Returns the leaf hash in the slot at `index`, all zeros if it is empty,
+    /// or None if `index` is out of range.
+    pub fn leaf(&self, index: usize) -> Option<Hash<N>> {
+        (index < self.capacity()).then(|| self.node(0, index))
+    }
+
+    /// Returns the number of steps in every proof.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Returns the number of slots, 2^depth.
+    pub fn capacity(&self) -> usize {
+        1 << self.depth
+    }
+
+    /// Returns the number of slots filled by `append` so far.
+    pub fn filled(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Returns true if every slot is filled.
+    pub fn is_full(&self) -> bool {
+        self.filled() == self.capacity()
+    }
+
+    /// The stored node at (level, index), or the empty subtree hash of that level.
+    fn node(&self, level: usize, index: usize) -> Hash<N> {
+        self.levels[level].get(index).copied().unwrap_or(self.empty[level])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashing::hash_key;
+    use crate::proof::verify_proof;
+    use crate::tree::Sha256Tree;
+
+    #[test]
+    fn every_proof_has_depth_steps() {
+        for depth in 0..=5 {
+            let mut fixed = Sha256Tree::fixed_depth(depth);
+            let capacity = fixed.capacity();
+
+            for filled in 0..=capacity {
+                // The same slots written into a tree that materializes every empty leaf
+                let mut slots = vec![[0u8; 32]; capacity];
+                for (index, slot) in slots.iter_mut().enumerate().take(filled) {
+                    *slot = hash_key(index as u64);
+                }
+                let mut full = Sha256Tree::new();
+                full.append_hashes(slots.iter().copied());
+                assert_eq!(Some(fixed.root()), full.root(), "depth {} filled {}", depth, filled);
+
+                for (index, slot) in slots.iter().enumerate() {
+                    let proof = fixed.proof(index).unwrap();
+                    assert_eq!(proof.len(), depth as usize);
+                    assert!(verify_proof(*slot, &proof, fixed.root()));
+                    assert_eq!(fixed.leaf(index), Some(*slot));
+                }
+                assert_eq!(fixed.proof(capacity), None);
+
+                if filled < capacity {
+                    assert_eq!(fixed.append(filled as u64), Ok(filled));
+                }
+            }
+
+            assert!(fixed.is_full());
+            assert_eq!(fixed.append(0u64), Err(MerkleError::Full));
+            assert_eq!(fixed.append_hash([0; 32]), Err(MerkleError::Full));
+        }
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn deep_trees_are_built_lazily() {
+        // The 2^40 empty leaves alone would take 32 TiB if materialized
+        let mut fixed = Sha256Tree::fixed_depth(40);
+        assert_eq!(fixed.capacity(), 1 << 40);
+
+        for key in 0..3u64 {
+            fixed.append(key).unwrap();
+        }
+        for index in [0, 2, 3, (1 << 40) - 1] {
+            let proof = fixed.proof(index).unwrap();
+            assert_eq!(proof.len(), 40);
+            assert!(verify_proof(fixed.leaf(index).unwrap(), &proof, fixed.root()));
+        }
+    }
+}
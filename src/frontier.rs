@@ -0,0 +1,235 @@
+/*
+    Frontier
+
+    A tree of n leaves is made of one perfect subtree per set bit of n, largest first,
+    the same "peaks" a Merkle Mountain Range keeps.  For n = 6 they cover leaves [0, 4)
+    and [4, 6).  Appending never changes a peak; it only merges equal-sized ones, so the
+    peaks alone are enough to keep accumulating leaves without storing the whole tree.
+
+    root_from_frontier rebuilds root() from the peaks and the leaf count, for either
+    policy: a tree that duplicates lone nodes rehashes the smaller peaks on its right edge,
+    so its root depends on the size as well as the peaks.
+
+    Bagging, as in a Merkle Mountain Range, instead folds the peaks from the right:
+        bag = H(p0 || H(p1 || ... H(p(k-2) || p(k-1))))
+    For a tree that promotes lone nodes (OddNodePolicy::Promote, rfc6962()) this is
+    exactly root(), otherwise it is a separate commitment to the peaks.
+
+    MMR proofs:
+        - every leaf lies under exactly one peak, and a peak is a perfect subtree, so the
+          leaf's path up to its peak has one sibling per level and no lone nodes
+        - mmr_proof(index) is that path, the first log2(peak size) steps of proof(index)
+        - verify_mmr_proof folds it and checks the result is a peak of that size, and
+          NodeHasher::verify_mmr_proof does the same for a tree with a runtime hasher
+*/
+
+use alloc::vec::Vec;
+
+use sha2::Sha256;
+use sha2::digest::Digest;
+
+use crate::consistency::{peaks, root_from_peaks};
+use crate::hashing::{Hash, ct_eq, hash_internal_with};
+use crate::proof::fold_proof;
+use crate::tree::{MerkleTree, OddNodePolicy};
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Returns the hashes of the perfect subtrees making up the tree, largest (leftmost) first.
+    /// Empty for an empty tree.
+    pub fn frontier(&self) -> Vec<Hash<N>> {
+        peaks(self.len())
+            .map(|(level, index)| self.levels[level][index])
+            .collect()
+    }
+
+    /// Returns every peak as (leaf count, hash), largest (leftmost) first, like a Merkle
+    /// Mountain Range.  The leaf counts are the set bits of `len()`, each a power of two.
+    pub fn mmr_peaks(&self) -> Vec<(usize, Hash<N>)> {
+        peaks(self.len())
+            .map(|(level, index)| (1 << level, self.levels[level][index]))
+            .collect()
+    }
+
+    /// Returns `mmr_peaks()` together with the peaks bagged like `bag_peaks_with`,
+    /// i.e. folded right to left: H(p0 || H(p1 || ... H(p(k-2) || p(k-1)))), hashing with
+    /// the tree's own node function.  The bagged root is None for an empty tree.
+    pub fn peaks_and_root(&self) -> (Vec<(usize, Hash<N>)>, Option<Hash<N>>) {
+        let peaks = self.mmr_peaks();
+        let hashes: Vec<Hash<N>> = peaks.iter().map(|&(_, hash)| hash).collect();
+        let root = bag_by(&hashes, |left, right| self.hash_node(left, right));
+        (peaks, root)
+    }
+
+    /// Returns the path from the leaf at `index` up to the peak covering it,
+    /// or None if `index` is out of range.  Check it with `verify_mmr_proof`.
+    pub fn mmr_proof(&self, index: usize) -> Option<Vec<(Hash<N>, bool)>> {
+        let mut path = self.proof(index)?;
+
+        // The covering peak is the first whose leaf range ends past index
+        let mut start = 0;
+        let (level, _) = peaks(self.len()).find(|&(level, _)| {
+            start += 1 << level;
+            index < start
+        })?;
+
+        path.truncate(level);
+        Some(path)
+    }
+}
+
+/// Checks that `leaf` hashes up to one of `peaks` (as returned by `mmr_peaks`) along
+/// `proof` from `mmr_proof`, and that the peak covers 2^(proof length) leaves.
+/// Expects a SHA-256 tree.
+pub fn verify_mmr_proof(leaf: Hash, proof: &[(Hash, bool)], peaks: &[(usize, Hash)]) -> bool {
+    verify_mmr_proof_with::<Sha256, 32>(leaf, proof, peaks)
+}
+
+/// Same as `verify_mmr_proof`, for peaks hashed with the digest `D` into N-byte hashes.
+pub fn verify_mmr_proof_with<D: Digest, const N: usize>(
+    leaf: Hash<N>,
+    proof: &[(Hash<N>, bool)],
+    peaks: &[(usize, Hash<N>)],
+) -> bool {
+    verify_mmr_proof_by(leaf, proof, peaks, hash_internal_with::<D, N>)
+}
+
+/// Same as `verify_mmr_proof_with`, hashing nodes with `hash_node`.
+pub(crate) fn verify_mmr_proof_by<const N: usize>(
+    leaf: Hash<N>,
+    proof: &[(Hash<N>, bool)],
+    peaks: &[(usize, Hash<N>)],
+    hash_node: impl Fn(Hash<N>, Hash<N>) -> Hash<N>,
+) -> bool {
+    let Some(size) = 1usize.checked_shl(proof.len() as u32) else {
+        return false;
+    };
+
+    let computed = fold_proof(leaf, proof.iter().copied(), hash_node);
+    peaks
+        .iter()
+        .any(|(leaves, peak)| *leaves == size && ct_eq(&computed, peak))
+}
+
+/// Rebuilds the root of a default SHA-256 tree of `size` leaves from its `frontier()`,
+/// equal to `root()` at that size.  None if `peaks` isn't one hash per set bit of `size`.
+pub fn root_from_frontier(peaks: &[Hash], size: usize) -> Option<Hash> {
+    root_from_frontier_with::<Sha256, 32>(OddNodePolicy::Duplicate, peaks, size)
+}
+
+/// Same as `root_from_frontier`, for a tree hashed with the digest `D` using `policy`.
+pub fn root_from_frontier_with<D: Digest, const N: usize>(
+    policy: OddNodePolicy,
+    peaks: &[Hash<N>],
+    size: usize,
+) -> Option<Hash<N>> {
+    root_from_peaks(policy, peaks, size, hash_internal_with::<D, N>)
+}
+
+/// Bags peaks (left to right) into a single SHA-256 hash, or None if there are none.
+/// Matches `root()` for a SHA-256 tree that promotes lone nodes.
+pub fn bag_peaks(peaks: &[Hash]) -> Option<Hash> {
+    bag_peaks_with::<Sha256, 32>(peaks)
+}
+
+/// Same as `bag_peaks`, for peaks hashed with the digest `D` into N-byte hashes.
+pub fn bag_peaks_with<D: Digest, const N: usize>(peaks: &[Hash<N>]) -> Option<Hash<N>> {
+    bag_by(peaks, hash_internal_with::<D, N>)
+}
+
+/// Bags `peaks`, hashing each pair with `hash_node`.
+fn bag_by<const N: usize>(
+    peaks: &[Hash<N>],
+    hash_node: impl Fn(Hash<N>, Hash<N>) -> Hash<N>,
+) -> Option<Hash<N>> {
+    let (&last, rest) = peaks.split_last()?;
+
+    // Fold from the smallest peak up to the largest
+    Some(rest.iter().rev().fold(last, |acc, &peak| hash_node(peak, acc)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::Sha256Tree;
+
+    #[test]
+    fn root_from_frontier_matches_root() {
+        for policy in [OddNodePolicy::Duplicate, OddNodePolicy::Promote] {
+            let mut tree = Sha256Tree::with_policy(policy);
+            assert_eq!(root_from_frontier_with::<Sha256, 32>(policy, &tree.frontier(), 0), None);
+
+            for key in 0..70u64 {
+                tree.append(key);
+                let frontier = tree.frontier();
+                let rebuilt = root_from_frontier_with::<Sha256, 32>(policy, &frontier, tree.len());
+                assert_eq!(rebuilt, tree.root(), "{:?} at {} leaves", policy, tree.len());
+            }
+        }
+    }
+
+    #[test]
+    fn frontier_accumulates_further_leaves() {
+        let mut tree = Sha256Tree::new();
+        tree.append_many(&(0..11).collect::<Vec<_>>());
+        let size = tree.len();
+        let frontier = tree.frontier();
+
+        // Later appends never change the old peaks, so the old root stays recoverable
+        tree.append_many(&(11..40).collect::<Vec<_>>());
+        assert_eq!(root_from_frontier(&frontier, size), tree.root_at(size));
+    }
+
+    #[test]
+    fn peaks_and_root_uses_the_tree_node_hasher() {
+        use crate::hasher::{LeafHasher, NodeHasher};
+        use sha2::Sha512;
+
+        let node = NodeHasher::<32>::from_digest::<Sha512>();
+        let mut tree = Sha256Tree::with_hashers(LeafHasher::default(), node.clone());
+        tree.append_many(&(0..13).collect::<Vec<_>>());
+
+        let (peaks, root) = tree.peaks_and_root();
+        let hashes: Vec<Hash> = peaks.iter().map(|&(_, hash)| hash).collect();
+        assert_eq!(root, bag_by(&hashes, |left, right| node.hash(left, right)));
+        assert_eq!(root, bag_peaks_with::<Sha512, 32>(&hashes));
+        assert_ne!(root, bag_peaks(&hashes));
+
+        // A promoting tree's bag is its root, whatever node function it uses
+        let mut promoted = Sha256Tree::with_hashers(LeafHasher::default(), node);
+        promoted.policy = OddNodePolicy::Promote;
+        promoted.append_many(&(0..13).collect::<Vec<_>>());
+        assert_eq!(promoted.peaks_and_root().1, promoted.root());
+    }
+
+    #[test]
+    fn mmr_proofs_verify_with_the_tree_node_hasher() {
+        use crate::hasher::{LeafHasher, NodeHasher};
+        use crate::hashing::hash_key;
+        use sha2::Sha512;
+
+        let node = NodeHasher::<32>::from_digest::<Sha512>();
+        let mut tree = Sha256Tree::with_hashers(LeafHasher::default(), node.clone());
+        tree.append_many(&(0..13).collect::<Vec<_>>());
+        let peaks = tree.mmr_peaks();
+
+        for index in 0..tree.len() {
+            let leaf = hash_key(index as u64);
+            let proof = tree.mmr_proof(index).unwrap();
+            assert!(tree.node_hasher().verify_mmr_proof(leaf, &proof, &peaks), "leaf {}", index);
+            assert!(!node.verify_mmr_proof(hash_key(99), &proof, &peaks), "leaf {}", index);
+
+            // Only the single-leaf peak has no node to hash, so D alone can't check the rest
+            let digest_only = verify_mmr_proof(leaf, &proof, &peaks);
+            assert_eq!(digest_only, proof.is_empty(), "leaf {}", index);
+        }
+    }
+
+    #[test]
+    fn bagged_peaks_match_promote_root() {
+        let mut tree = Sha256Tree::rfc6962();
+        for key in 0..70u64 {
+            tree.append(key);
+            assert_eq!(bag_peaks(&tree.frontier()), tree.root());
+        }
+    }
+}
@@ -0,0 +1,307 @@
+/*
+    Runtime hashers
+
+    The digest D is fixed at compile time.  For keyed hashes or HMACs whose key is only
+    known at runtime, a tree can instead carry a Hasher: a leaf closure and a node closure,
+    stored behind Arc so the tree stays Clone, Send and Sync.
+
+    Every hash the tree computes goes through the closures when they are set:
+        - leaf_fn(data) for append, append_bytes and append_many, on the key bytes or raw data
+        - node_fn(left, right) for every internal node, including duplicated lone nodes
+
+    The closures replace the domain-separation prefixes too, so a leaf_fn that wants them
+    must add them itself; a with_domain label is ignored.  The hasher isn't serialized,
+    so a deserialized tree hashes with D again, and save / save_full refuse a tree that
+    has one.  Proofs from such a tree only verify through Hasher::verify_proof, the free
+    verify functions always use a Digest.
+
+    Split leaf and node hashing:
+        - a Hasher is a LeafHasher plus a NodeHasher, each one closure
+        - LeafHasher::from_digest::<L>() and NodeHasher::from_digest::<M>() hash with two
+          different digests but keep the 0x00 / 0x01 prefixes, e.g. a cheap leaf digest
+          under a stronger node digest.  Both default to SHA-256.
+        - with_hashers(leaf, node) configures a tree with the pair at construction
+        - a proof only ever folds internal nodes, so tree.node_hasher().verify_proof checks
+          it; the verifier never needs the leaf half
+*/
+
+use alloc::sync::Arc;
+use core::fmt;
+
+use sha2::Sha256;
+use sha2::digest::Digest;
+
+use crate::hashing::{
+    Hash, ct_eq, hash_internal_with, hash_leaf_bytes_with, hash_leaf_in_domain_with,
+};
+use crate::frontier::verify_mmr_proof_by;
+use crate::proof::fold_proof;
+use crate::tree::MerkleTree;
+
+type LeafFn<const N: usize> = dyn Fn(&[u8]) -> Hash<N> + Send + Sync;
+type NodeFn<const N: usize> = dyn Fn(Hash<N>, Hash<N>) -> Hash<N> + Send + Sync;
+
+/// The leaf half of a Hasher: hashes leaf data into a leaf hash.
+pub struct LeafHasher<const N: usize = 32> {
+    leaf_fn: Arc<LeafFn<N>>,
+}
+
+/// The node half of a Hasher: hashes two children into their parent.
+pub struct NodeHasher<const N: usize = 32> {
+    node_fn: Arc<NodeFn<N>>,
+}
+
+/// A pair of leaf and node hash functions supplied at runtime.
+pub struct Hasher<const N: usize = 32> {
+    leaf: LeafHasher<N>,
+    node: NodeHasher<N>,
+}
+
+impl<const N: usize> LeafHasher<N> {
+    /// Wraps `leaf_fn`, hashing leaf data.
+    pub fn new(leaf_fn: impl Fn(&[u8]) -> Hash<N> + Send + Sync + 'static) -> Self {
+        LeafHasher {
+            leaf_fn: Arc::new(leaf_fn),
+        }
+    }
+
+    /// Hashes leaves as H(0x00 || data) with the digest `D`, like `hash_leaf_bytes_with`.
+    pub fn from_digest<D: Digest>() -> Self {
+        let leaf_fn: fn(&[u8]) -> Hash<N> = hash_leaf_bytes_with::<D, N>;
+        LeafHasher::new(leaf_fn)
+    }
+
+    /// Hashes leaf data.
+    pub fn hash(&self, data: &[u8]) -> Hash<N> {
+        (self.leaf_fn)(data)
+    }
+}
+
+impl<const N: usize> NodeHasher<N> {
+    /// Wraps `node_fn`, hashing two children into their parent.
+    pub fn new(node_fn: impl Fn(Hash<N>, Hash<N>) -> Hash<N> + Send + Sync + 'static) -> Self {
+        NodeHasher {
+            node_fn: Arc::new(node_fn),
+        }
+    }
+
+    /// Hashes nodes as H(0x01 || left || right) with the digest `D`, like `hash_internal_with`.
+    pub fn from_digest<D: Digest>() -> Self {
+        let node_fn: fn(Hash<N>, Hash<N>) -> Hash<N> = hash_internal_with::<D, N>;
+        NodeHasher::new(node_fn)
+    }
+
+    /// Hashes two child hashes into their parent.
+    pub fn hash(&self, left: Hash<N>, right: Hash<N>) -> Hash<N> {
+        (self.node_fn)(left, right)
+    }
+
+    /// Folds `proof` into `leaf` with the node function, like `proof_root`.
+    pub fn proof_root(&self, leaf: Hash<N>, proof: &[(Hash<N>, bool)]) -> Hash<N> {
+        fold_proof(leaf, proof.iter().copied(), |left, right| self.hash(left, right))
+    }
+
+    /// Checks a proof from a tree whose internal nodes use this function, like `verify_proof`.
+    pub fn verify_proof(
+        &self,
+        leaf: Hash<N>,
+        proof: &[(Hash<N>, bool)],
+        expected_root: Hash<N>,
+    ) -> bool {
+        ct_eq(&self.proof_root(leaf, proof), &expected_root)
+    }
+
+    /// Checks an `mmr_proof` from a tree whose internal nodes use this function against its
+    /// `mmr_peaks`, like `verify_mmr_proof`.
+    pub fn verify_mmr_proof(
+        &self,
+        leaf: Hash<N>,
+        proof: &[(Hash<N>, bool)],
+        peaks: &[(usize, Hash<N>)],
+    ) -> bool {
+        verify_mmr_proof_by(leaf, proof, peaks, |left, right| self.hash(left, right))
+    }
+}
+
+impl<const N: usize> Hasher<N> {
+    /// Wraps `leaf_fn`, hashing leaf data, and `node_fn`, hashing two children into their parent.
+    pub fn new(
+        leaf_fn: impl Fn(&[u8]) -> Hash<N> + Send + Sync + 'static,
+        node_fn: impl Fn(Hash<N>, Hash<N>) -> Hash<N> + Send + Sync + 'static,
+    ) -> Self {
+        Hasher::from_parts(LeafHasher::new(leaf_fn), NodeHasher::new(node_fn))
+    }
+
+    /// Pairs a leaf hasher with a node hasher, e.g. each from a different digest.
+    pub fn from_parts(leaf: LeafHasher<N>, node: NodeHasher<N>) -> Self {
+        Hasher { leaf, node }
+    }
+
+    /// Returns the leaf half.
+    pub fn leaf_hasher(&self) -> &LeafHasher<N> {
+        &self.leaf
+    }
+
+    /// Returns the node half, all a proof verifier needs.
+    pub fn node_hasher(&self) -> &NodeHasher<N> {
+        &self.node
+    }
+
+    /// Hashes leaf data with the leaf function.
+    pub fn hash_leaf(&self, data: &[u8]) -> Hash<N> {
+        self.leaf.hash(data)
+    }
+
+    /// Hashes two child hashes with the node function.
+    pub fn hash_node(&self, left: Hash<N>, right: Hash<N>) -> Hash<N> {
+        self.node.hash(left, right)
+    }
+
+    /// Folds `proof` into `leaf` with the node function, like `proof_root`.
+    pub fn proof_root(&self, leaf: Hash<N>, proof: &[(Hash<N>, bool)]) -> Hash<N> {
+        self.node.proof_root(leaf, proof)
+    }
+
+    /// Checks a proof from a tree built with this hasher, like `verify_proof`.
+    pub fn verify_proof(
+        &self,
+        leaf: Hash<N>,
+        proof: &[(Hash<N>, bool)],
+        expected_root: Hash<N>,
+    ) -> bool {
+        self.node.verify_proof(leaf, proof, expected_root)
+    }
+}
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Creates an empty tree hashing leaves with `leaf_fn` and internal nodes with `node_fn`
+    /// instead of the digest D.
+    pub fn with_hasher(
+        leaf_fn: impl Fn(&[u8]) -> Hash<N> + Send + Sync + 'static,
+        node_fn: impl Fn(Hash<N>, Hash<N>) -> Hash<N> + Send + Sync + 'static,
+    ) -> Self {
+        let mut tree = Self::new();
+        tree.hasher = Some(Hasher::new(leaf_fn, node_fn));
+        tree
+    }
+
+    /// Creates an empty tree hashing leaves with `leaf` and internal nodes with `node`,
+    /// e.g. `LeafHasher::from_digest::<L>()` and `NodeHasher::from_digest::<M>()`.
+    pub fn with_hashers(leaf: LeafHasher<N>, node: NodeHasher<N>) -> Self {
+        let mut tree = Self::new();
+        tree.hasher = Some(Hasher::from_parts(leaf, node));
+        tree
+    }
+
+    /// Returns the function hashing this tree's internal nodes: the runtime node hasher if
+    /// set, else D.  Its `verify_proof` checks this tree's proofs.
+    pub fn node_hasher(&self) -> NodeHasher<N> {
+        match &self.hasher {
+            Some(hasher) => hasher.node.clone(),
+            None => NodeHasher::from_digest::<D>(),
+        }
+    }
+
+    /// Returns the runtime hasher, or None if the tree hashes with its digest.
+    pub fn hasher(&self) -> Option<&Hasher<N>> {
+        self.hasher.as_ref()
+    }
+
+    /// Hashes leaf data with the runtime hasher if set, else with D in the tree's domain.
+    pub(crate) fn hash_leaf(&self, data: &[u8]) -> Hash<N> {
+        leaf_hash::<D, N>(self.hasher.as_ref(), &self.domain, data)
+    }
+
+    /// Hashes two children with the runtime hasher if set, else with D.
+    pub(crate) fn hash_node(&self, left: Hash<N>, right: Hash<N>) -> Hash<N> {
+        node_hash::<D, N>(self.hasher.as_ref(), left, right)
+    }
+
+    /// Returns the parent of a node with no right sibling, see `OddNodePolicy`.
+    pub(crate) fn lone_parent(&self, node: &Hash<N>) -> Hash<N> {
+        self.policy.lone_parent_by(node, |left, right| self.hash_node(left, right))
+    }
+}
+
+/// Hashes leaf data with `hasher`, or with D in the `domain` label when there is none.
+pub(crate) fn leaf_hash<D: Digest, const N: usize>(
+    hasher: Option<&Hasher<N>>,
+    domain: &[u8],
+    data: &[u8],
+) -> Hash<N> {
+    match hasher {
+        Some(hasher) => hasher.hash_leaf(data),
+        None => hash_leaf_in_domain_with::<D, N>(domain, data),
+    }
+}
+
+/// Hashes two children with `hasher`, or with D when there is none.
+pub(crate) fn node_hash<D: Digest, const N: usize>(
+    hasher: Option<&Hasher<N>>,
+    left: Hash<N>,
+    right: Hash<N>,
+) -> Hash<N> {
+    match hasher {
+        Some(hasher) => hasher.hash_node(left, right),
+        None => hash_internal_with::<D, N>(left, right),
+    }
+}
+
+impl Default for LeafHasher {
+    fn default() -> Self {
+        LeafHasher::from_digest::<Sha256>()
+    }
+}
+
+impl Default for NodeHasher {
+    fn default() -> Self {
+        NodeHasher::from_digest::<Sha256>()
+    }
+}
+
+impl Default for Hasher {
+    fn default() -> Self {
+        Hasher::from_parts(LeafHasher::default(), NodeHasher::default())
+    }
+}
+
+// Cloning shares the closures.
+impl<const N: usize> Clone for LeafHasher<N> {
+    fn clone(&self) -> Self {
+        LeafHasher {
+            leaf_fn: Arc::clone(&self.leaf_fn),
+        }
+    }
+}
+
+impl<const N: usize> Clone for NodeHasher<N> {
+    fn clone(&self) -> Self {
+        NodeHasher {
+            node_fn: Arc::clone(&self.node_fn),
+        }
+    }
+}
+
+impl<const N: usize> Clone for Hasher<N> {
+    fn clone(&self) -> Self {
+        Hasher::from_parts(self.leaf.clone(), self.node.clone())
+    }
+}
+
+impl<const N: usize> fmt::Debug for LeafHasher<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LeafHasher(<closure>)")
+    }
+}
+
+impl<const N: usize> fmt::Debug for NodeHasher<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("NodeHasher(<closure>)")
+    }
+}
+
+impl<const N: usize> fmt::Debug for Hasher<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Hasher(<closures>)")
+    }
+}
@@ -0,0 +1,274 @@
+///Hash function
+use alloc::format;
+use alloc::string::String;
+use sha2::Sha256;
+use sha2::digest::generic_array::typenum::Unsigned;
+use sha2::digest::{Digest, OutputSizeUser};
+use subtle::ConstantTimeEq;
+
+use crate::error::HexError;
+
+/// An N-byte hash value, 32 bytes (SHA-256 output) by default.
+/// Digests with longer outputs are truncated to their first N bytes.
+pub type Hash<const N: usize = 32> = [u8; N];
+
+pub(crate) type Key = u64;
+
+/// Byte order used to turn a u64 key into the 8 bytes of leaf data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Endianness {
+    /// Most significant byte first, `u64::to_be_bytes` (the original encoding).
+    #[default]
+    Big,
+    /// Least significant byte first, `u64::to_le_bytes`.
+    Little,
+}
+
+impl Endianness {
+    /// Serializes `key` in this byte order.
+    pub fn key_bytes(self, key: Key) -> [u8; 8] {
+        match self {
+            Endianness::Big => key.to_be_bytes(),
+            Endianness::Little => key.to_le_bytes(),
+        }
+    }
+}
+
+/// Domain-separation byte prepended to leaf data before hashing.
+pub(crate) const LEAF_PREFIX: u8 = 0x00;
+
+/// Domain-separation byte prepended to two child hashes before hashing.
+pub(crate) const NODE_PREFIX: u8 = 0x01;
+
+
+/*
+    Helper functions
+*/
+
+/// Hash a u64 key into a SHA-256 leaf Hash, the way `append` does.
+/// Hash = H(0x00 || 8 big-endian key bytes)
+pub fn hash_key(key: Key) -> Hash {
+    hash_key_with::<Sha256, 32>(key)
+}
+
+/// Same as `hash_key`, using the digest `D` truncated to N bytes.
+pub fn hash_key_with<D: Digest, const N: usize>(key: Key) -> Hash<N> {
+
+    //convert key into bytes.  Big Endian
+    let key_bytes = Endianness::Big.key_bytes(key);
+
+    //a u64 key is just 8 bytes of leaf data
+    hash_leaf_bytes_with::<D, N>(&key_bytes)
+}
+
+/// Hash arbitrary leaf data into a SHA-256 Hash.
+/// Hash = H(0x00 || data)
+pub fn hash_leaf_bytes(data: &[u8]) -> Hash {
+    hash_leaf_bytes_with::<Sha256, 32>(data)
+}
+
+/// Same as `hash_leaf_bytes`, using the digest `D` truncated to N bytes.
+pub fn hash_leaf_bytes_with<D: Digest, const N: usize>(data: &[u8]) -> Hash<N> {
+
+    //Construct a hasher
+    let mut hasher = D::new();
+
+    //leaf domain separator, so a leaf can never be passed off as an internal node
+    hasher.update([LEAF_PREFIX]);
+
+    //Hash the leaf data
+    hasher.update(data);
+
+    truncate_output::<D, N>(hasher)
+}
+
+/// Hash a u64 key into a SHA-256 leaf of the tree made with `with_domain(label)`.
+/// Hash = H(label length as 8 big-endian bytes || label || 0x00 || 8 big-endian key bytes),
+/// or `hash_key(key)` for an empty label.
+pub fn hash_key_in_domain(label: &[u8], key: Key) -> Hash {
+    hash_key_in_domain_with::<Sha256, 32>(label, key)
+}
+
+/// Same as `hash_key_in_domain`, using the digest `D` truncated to N bytes.
+pub fn hash_key_in_domain_with<D: Digest, const N: usize>(label: &[u8], key: Key) -> Hash<N> {
+    hash_leaf_in_domain_with::<D, N>(label, &Endianness::Big.key_bytes(key))
+}
+
+/// Same as `hash_leaf_bytes_with`, with the domain `label` mixed in first unless it is empty.
+pub(crate) fn hash_leaf_in_domain_with<D: Digest, const N: usize>(
+    label: &[u8],
+    data: &[u8],
+) -> Hash<N> {
+    if label.is_empty() {
+        return hash_leaf_bytes_with::<D, N>(data);
+    }
+
+    let mut hasher = D::new();
+
+    //length first, so no label can be a prefix of another label plus leaf data
+    hasher.update((label.len() as u64).to_be_bytes());
+    hasher.update(label);
+
+    //then the usual leaf domain separator and data
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(data);
+
+    truncate_output::<D, N>(hasher)
+}
+
+/// Hash two child hashes into their SHA-256 parent hash, one step of a proof.
+/// Hash = H(0x01 || left || right)
+pub fn hash_internal(left: Hash, right: Hash) -> Hash {
+    hash_internal_with::<Sha256, 32>(left, right)
+}
+
+/// Same as `hash_internal`, using the digest `D` truncated to N bytes.
+pub fn hash_internal_with<D: Digest, const N: usize>(left: Hash<N>, right: Hash<N>) -> Hash<N> {
+
+    //Construct a hasher
+    let mut hasher = D::new();
+
+    //internal node domain separator
+    hasher.update([NODE_PREFIX]);
+
+    //add left hash to hasher
+    hasher.update(left);
+
+    //add right hash to hasher, this will concatenate with left hash to yield (left || right)
+    hasher.update(right);
+
+    truncate_output::<D, N>(hasher)
+}
+
+/// Finalizes `hasher` and keeps exactly the first N bytes of its output.
+fn truncate_output<D: Digest, const N: usize>(hasher: D) -> Hash<N> {
+    // Fails to compile when N is wider than the digest output
+    const {
+        assert!(
+            N <= <<D as OutputSizeUser>::OutputSize as Unsigned>::USIZE,
+            "hash width N is larger than the digest output"
+        )
+    };
+
+    //finalize
+    let result = hasher.finalize();
+
+    //Convert GenericArray<u8, OutputSize> into [u8; N]
+    let mut hash = [0u8; N];
+    hash.copy_from_slice(&result[..N]);
+
+    hash
+}
+
+/// Compares two hashes without stopping at the first differing byte, so the time taken
+/// doesn't tell an attacker how much of a forged root was right.
+/// Delegates to `subtle`, which is built to keep the optimizer from adding an early exit.
+pub(crate) fn ct_eq<const N: usize>(a: &Hash<N>, b: &Hash<N>) -> bool {
+    a[..].ct_eq(&b[..]).into()
+}
+
+/// Counts the bits that differ between two hashes, e.g. two roots before and after
+/// changing one leaf.  A good digest flips about half of them (128 for SHA-256).
+pub fn root_hamming_distance<const N: usize>(a: Hash<N>, b: Hash<N>) -> u32 {
+    a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum()
+}
+
+/// Formats a hash as a lowercase hex string.
+pub fn hash_to_hex(hash: &[u8]) -> String {
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Parses a 64-character hex string (as printed by `hash_to_hex`) into a 32-byte Hash.
+pub fn hash_from_hex(s: &str) -> Result<Hash, HexError> {
+    hash_from_hex_with::<32>(s)
+}
+
+/// Same as `hash_from_hex`, for N-byte hashes (2 * N hex characters).
+pub fn hash_from_hex_with<const N: usize>(s: &str) -> Result<Hash<N>, HexError> {
+    //two hex characters per byte
+    let found = s.chars().count();
+    if found != 2 * N {
+        return Err(HexError::InvalidLength { expected: 2 * N, found });
+    }
+
+    //reject anything that isn't a hex digit, reporting where it was
+    if let Some((index, found)) = s.chars().enumerate().find(|(_, c)| !c.is_ascii_hexdigit()) {
+        return Err(HexError::InvalidChar { index, found });
+    }
+
+    //every character is ASCII now, so byte offsets line up with character offsets
+    let mut hash = [0u8; N];
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16)
+            .expect("hex digits were validated above");
+    }
+
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The data a forger would present as a leaf to pass off the node over `left` and `right`.
+    fn concat(left: &Hash, right: &Hash) -> [u8; 64] {
+        let mut data = [0u8; 64];
+        data[..32].copy_from_slice(left);
+        data[32..].copy_from_slice(right);
+        data
+    }
+
+    #[test]
+    fn leaf_never_equals_node_over_same_bytes() {
+        for a in 0..16 {
+            for b in 0..16 {
+                let (left, right) = (hash_key(a), hash_key(b));
+                let data = concat(&left, &right);
+
+                assert_ne!(hash_leaf_bytes(&data), hash_internal(left, right));
+            }
+        }
+    }
+
+    #[test]
+    fn prefixes_are_hashed_in_front_of_the_data() {
+        let (left, right) = (hash_key(1), hash_key(2));
+        let data = concat(&left, &right);
+
+        let prefixed = |prefix: u8| -> Hash {
+            Sha256::new().chain_update([prefix]).chain_update(data).finalize().into()
+        };
+        let (leaf, node) = (prefixed(LEAF_PREFIX), prefixed(NODE_PREFIX));
+
+        assert_eq!(hash_leaf_bytes(&data), leaf);
+        assert_eq!(hash_internal(left, right), node);
+        assert_ne!(LEAF_PREFIX, NODE_PREFIX);
+
+        // Without the prefixes both would be H(left || right)
+        let bare: Hash = Sha256::digest(data).into();
+        assert_ne!(leaf, bare);
+        assert_ne!(node, bare);
+    }
+
+    #[test]
+    fn ct_eq_accepts_equal_hashes() {
+        let hash = hash_key(7);
+        assert!(ct_eq(&hash, &hash));
+        assert!(ct_eq(&[0u8; 32], &[0u8; 32]));
+        assert!(ct_eq::<0>(&[], &[]));
+    }
+
+    #[test]
+    fn ct_eq_rejects_any_single_bit_off() {
+        let hash = hash_key(7);
+        for byte in 0..32 {
+            for bit in 0..8 {
+                let mut forged = hash;
+                forged[byte] ^= 1 << bit;
+                assert!(!ct_eq(&hash, &forged), "byte {} bit {}", byte, bit);
+                assert!(!ct_eq(&forged, &hash), "byte {} bit {}", byte, bit);
+            }
+        }
+    }
+}
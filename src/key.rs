@@ -0,0 +1,127 @@
+/*
+    Key encodings
+
+    A key becomes a leaf by hashing its leaf bytes: leaf = H(0x00 || to_leaf_bytes()).
+    MerkleKey lets any type pick those bytes, so append_key works the same for integers,
+    byte strings, text or a caller's own record type:
+        - u64: 8 bytes, u32: 4 bytes, both big-endian by default
+        - i64: its 8 two's-complement bytes, exactly like the u64 with the same bits
+        - f64: its 8 IEEE 754 bytes (f64::to_bits), every NaN first replaced by the
+          canonical quiet NaN 0x7ff8000000000000; 0.0 and -0.0 stay distinct leaves
+        - [u8], [u8; L]: the bytes as-is, the same leaf as append_bytes
+        - str: its UTF-8 bytes
+        - &K: the encoding of K, so slices and strings can be passed by reference
+
+    The encodings don't carry their type, so 5u32 and 5u64 give different leaves but
+    b"\0\0\0\x05" and 5u32 give the same one.  That is also why append_key needs integer
+    keys suffixed (append_key(5u32)): a bare literal would silently pick one width.
+    append itself keeps taking a u64, so append(5) means the same as it always has.
+
+    Integer keys follow the tree's Endianness through to_leaf_bytes_in; other encodings
+    ignore it.
+*/
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::hashing::Endianness;
+
+/// A value that can be appended as a leaf, hashed through its leaf bytes.
+pub trait MerkleKey {
+    /// Encodes the key as the data hashed into its leaf.
+    fn to_leaf_bytes(&self) -> Vec<u8>;
+
+    /// Same as `to_leaf_bytes`, for a tree serializing integer keys in `endianness`.
+    /// Defaults to `to_leaf_bytes`, only byte-order dependent encodings override it.
+    fn to_leaf_bytes_in(&self, endianness: Endianness) -> Vec<u8> {
+        let _ = endianness;
+        self.to_leaf_bytes()
+    }
+}
+
+impl MerkleKey for u64 {
+    fn to_leaf_bytes(&self) -> Vec<u8> {
+        self.to_leaf_bytes_in(Endianness::Big)
+    }
+
+    fn to_leaf_bytes_in(&self, endianness: Endianness) -> Vec<u8> {
+        endianness.key_bytes(*self).to_vec()
+    }
+}
+
+impl MerkleKey for u32 {
+    fn to_leaf_bytes(&self) -> Vec<u8> {
+        self.to_leaf_bytes_in(Endianness::Big)
+    }
+
+    fn to_leaf_bytes_in(&self, endianness: Endianness) -> Vec<u8> {
+        match endianness {
+            Endianness::Big => self.to_be_bytes().to_vec(),
+            Endianness::Little => self.to_le_bytes().to_vec(),
+        }
+    }
+}
+
+impl MerkleKey for i64 {
+    fn to_leaf_bytes(&self) -> Vec<u8> {
+        self.to_leaf_bytes_in(Endianness::Big)
+    }
+
+    fn to_leaf_bytes_in(&self, endianness: Endianness) -> Vec<u8> {
+        (*self as u64).to_leaf_bytes_in(endianness)
+    }
+}
+
+/// Bit pattern every NaN is hashed as, so NaN payloads never change a leaf.
+const CANONICAL_NAN: u64 = 0x7ff8_0000_0000_0000;
+
+impl MerkleKey for f64 {
+    fn to_leaf_bytes(&self) -> Vec<u8> {
+        self.to_leaf_bytes_in(Endianness::Big)
+    }
+
+    fn to_leaf_bytes_in(&self, endianness: Endianness) -> Vec<u8> {
+        let bits = if self.is_nan() { CANONICAL_NAN } else { self.to_bits() };
+        bits.to_leaf_bytes_in(endianness)
+    }
+}
+
+impl MerkleKey for [u8] {
+    fn to_leaf_bytes(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+}
+
+impl<const L: usize> MerkleKey for [u8; L] {
+    fn to_leaf_bytes(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+}
+
+impl MerkleKey for Vec<u8> {
+    fn to_leaf_bytes(&self) -> Vec<u8> {
+        self.clone()
+    }
+}
+
+impl MerkleKey for str {
+    fn to_leaf_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl MerkleKey for String {
+    fn to_leaf_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl<K: MerkleKey + ?Sized> MerkleKey for &K {
+    fn to_leaf_bytes(&self) -> Vec<u8> {
+        (**self).to_leaf_bytes()
+    }
+
+    fn to_leaf_bytes_in(&self, endianness: Endianness) -> Vec<u8> {
+        (**self).to_leaf_bytes_in(endianness)
+    }
+}
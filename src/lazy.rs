@@ -0,0 +1,288 @@
+/*
+    Lazy mode
+
+    A regular tree keeps every level, so the nodes above the leaves roughly double its
+    memory.  LazyTree stores only the leaves and hashes the nodes it needs on demand:
+        - node(level, index) rehashes the 2^level leaves under that node
+        - root() rehashes every leaf, O(n) per call
+        - proof(index) hashes each sibling subtree once, also O(n) in total
+
+    Appends are O(1) since nothing above the leaves is kept up to date.  The methods
+    LazyTree has mirror MerkleTree's and return the same hashes and proofs, so switching
+    between the two only changes the memory / time tradeoff.
+
+    Supported: appending (append, append_bytes, append_hash, append_many), removing
+    (truncate, pop, clear), root, try_root, proof, node, leaf, leaves, index_of, contains,
+    len, is_empty, height, policy and endianness.  Anything else, e.g. consistency_proof,
+    multi_proof, frontier, validate or save, needs every level anyway: call into_tree()
+    for a MerkleTree with the same leaves and settings, a single rebuild.
+*/
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use sha2::Sha256;
+use sha2::digest::Digest;
+
+use crate::error::MerkleError;
+use crate::hasher::{Hasher, leaf_hash, node_hash};
+use crate::hashing::{Endianness, Hash, Key};
+use crate::key::MerkleKey;
+use crate::tree::{MerkleTree, OddNodePolicy, height_for, level_width};
+
+/// A Merkle tree that stores only its leaves and computes upper nodes when asked.
+pub struct LazyTree<D: Digest = Sha256, const N: usize = 32> {
+    leaves: Vec<Hash<N>>,
+    policy: OddNodePolicy,
+    endianness: Endianness,
+    domain: Vec<u8>,
+    hasher: Option<Hasher<N>>,
+    digest: PhantomData<D>,
+}
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Creates an empty tree in lazy mode, storing only the leaves.
+    pub fn new_lazy() -> LazyTree<D, N> {
+        MerkleTree::new().into_lazy()
+    }
+
+    /// Converts this tree to lazy mode, keeping its leaves and settings and dropping the upper levels.
+    pub fn into_lazy(mut self) -> LazyTree<D, N> {
+        let leaves = if self.levels.is_empty() {
+            Vec::new()
+        } else {
+            self.levels.swap_remove(0)
+        };
+
+        LazyTree {
+            leaves,
+            policy: self.policy,
+            endianness: self.endianness,
+            domain: self.domain,
+            hasher: self.hasher,
+            digest: PhantomData,
+        }
+    }
+}
+
+impl<D: Digest, const N: usize> LazyTree<D, N> {
+    /// Returns how this tree handles odd-length levels.
+    pub fn policy(&self) -> OddNodePolicy {
+        self.policy
+    }
+
+    /// Returns the byte order keys are serialized in before hashing.
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    /// Appends a new u64 key as a leaf, like `MerkleTree::append`.
+    pub fn append(&mut self, key: Key) {
+        self.append_key(key);
+    }
+
+    /// Appends a key of any `MerkleKey` type, hashed as its leaf bytes like
+    /// `MerkleTree::append_key`.
+    pub fn append_key<K: MerkleKey>(&mut self, key: K) {
+        self.append_hash(self.key_leaf(&key));
+    }
+
+    /// Appends arbitrary bytes as a leaf.
+    pub fn append_bytes(&mut self, data: &[u8]) {
+        self.append_hash(leaf_hash::<D, N>(self.hasher.as_ref(), &self.domain, data));
+    }
+
+    /// Appends an already-computed leaf hash as-is.
+    pub fn append_hash(&mut self, leaf: Hash<N>) {
+        self.leaves.push(leaf);
+    }
+
+    /// Appends many keys as leaves.
+    pub fn append_many(&mut self, keys: &[Key]) {
+        for &key in keys {
+            self.append(key);
+        }
+    }
+
+    /// Rolls the tree back to its first `len` leaves, like `MerkleTree::truncate`.
+    pub fn truncate(&mut self, len: usize) {
+        self.leaves.truncate(len);
+    }
+
+    /// Removes the most recently appended leaf and returns its hash, or None if the tree is empty.
+    pub fn pop(&mut self) -> Option<Hash<N>> {
+        self.leaves.pop()
+    }
+
+    /// Removes every leaf, keeping the tree's settings.
+    pub fn clear(&mut self) {
+        self.leaves.clear();
+    }
+
+    /// Computes the root hash, or None if the tree is empty.
+    pub fn root(&self) -> Option<Hash<N>> {
+        self.node(self.height().checked_sub(1)?, 0)
+    }
+
+    /// Computes the root hash, or MerkleError::Empty if the tree is empty.
+    pub fn try_root(&self) -> Result<Hash<N>, MerkleError> {
+        self.root().ok_or(MerkleError::Empty)
+    }
+
+    /// Computes the sibling path from the leaf at `index` up to the root,
+    /// or None if `index` is out of range.  Same steps as `MerkleTree::proof`.
+    pub fn proof(&self, index: usize) -> Option<Vec<(Hash<N>, bool)>> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let mut path = Vec::new();
+        let mut node_index = index;
+
+        // Walk every level except the root level
+        for level in 0..self.height() - 1 {
+            let width = level_width(self.len(), level);
+
+            if node_index.is_multiple_of(2) {
+                // Left child: sibling is to the right, or the node itself if it was duplicated
+                if node_index + 1 < width {
+                    path.push((self.subtree(level, node_index + 1), true));
+                } else if self.policy == OddNodePolicy::Duplicate {
+                    path.push((self.subtree(level, node_index), true));
+                }
+            } else {
+                // Right child: sibling is always the node to the left
+                path.push((self.subtree(level, node_index - 1), false));
+            }
+
+            // Move up to the parent's position
+            node_index /= 2;
+        }
+
+        Some(path)
+    }
+
+    /// Computes the node hash at (`level`, `index`), with level 0 being the leaves,
+    /// or None if either coordinate is out of range.
+    pub fn node(&self, level: usize, index: usize) -> Option<Hash<N>> {
+        if level >= self.height() || index >= level_width(self.len(), level) {
+            return None;
+        }
+        Some(self.subtree(level, index))
+    }
+
+    /// Returns the leaf hash at `index`, or None if out of range.
+    pub fn leaf(&self, index: usize) -> Option<Hash<N>> {
+        self.leaves.get(index).copied()
+    }
+
+    /// Returns the index of the first leaf holding `key`, or None if it isn't in the tree.
+    /// O(n), like `MerkleTree::index_of`.
+    pub fn index_of<K: MerkleKey>(&self, key: K) -> Option<usize> {
+        let leaf = self.key_leaf(&key);
+        self.leaves.iter().position(|candidate| *candidate == leaf)
+    }
+
+    /// Returns true if `key` has been appended.  O(n), see `index_of`.
+    pub fn contains<K: MerkleKey>(&self, key: K) -> bool {
+        self.index_of(key).is_some()
+    }
+
+    /// Iterates over the leaf hashes in insertion order.
+    pub fn leaves(&self) -> impl Iterator<Item = &Hash<N>> {
+        self.leaves.iter()
+    }
+
+    /// Returns the number of leaves in the tree.
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Returns true if the tree has no leaves.
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Returns the number of levels the full tree would have (0 if empty).
+    pub fn height(&self) -> usize {
+        height_for(self.len())
+    }
+
+    /// Builds the full tree from the stored leaves, a single rebuild.
+    pub fn into_tree(self) -> MerkleTree<D, N> {
+        let mut tree = MerkleTree::with_policy(self.policy);
+        tree.endianness = self.endianness;
+        tree.domain = self.domain;
+        tree.hasher = self.hasher;
+        tree.extend_leaves(self.leaves);
+        tree
+    }
+
+    /// Hashes `key` into a leaf the same way `MerkleTree::append` does.
+    fn key_leaf<K: MerkleKey + ?Sized>(&self, key: &K) -> Hash<N> {
+        leaf_hash::<D, N>(self.hasher.as_ref(), &self.domain, &key.to_leaf_bytes_in(self.endianness))
+    }
+
+    /// Hashes the subtree under (level, index).  Expects both to be in range.
+    fn subtree(&self, level: usize, index: usize) -> Hash<N> {
+        if level == 0 {
+            return self.leaves[index];
+        }
+
+        let left = self.subtree(level - 1, 2 * index);
+        if 2 * index + 1 < level_width(self.len(), level - 1) {
+            self.hash_node(left, self.subtree(level - 1, 2 * index + 1))
+        } else {
+            self.policy.lone_parent_by(&left, |left, right| self.hash_node(left, right))
+        }
+    }
+
+    /// Hashes two children the same way `MerkleTree` does.
+    fn hash_node(&self, left: Hash<N>, right: Hash<N>) -> Hash<N> {
+        node_hash::<D, N>(self.hasher.as_ref(), left, right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::Sha256Tree;
+
+    #[test]
+    fn lazy_matches_merkle_tree() {
+        for policy in [OddNodePolicy::Duplicate, OddNodePolicy::Promote] {
+            let mut tree = Sha256Tree::with_policy(policy);
+            let mut lazy = Sha256Tree::with_policy(policy).into_lazy();
+
+            for size in 0..=33u64 {
+                let context = (policy, size);
+                assert_eq!(lazy.len(), tree.len(), "{:?}", context);
+                assert_eq!(lazy.height(), tree.height(), "{:?}", context);
+                assert_eq!(lazy.root(), tree.root(), "{:?}", context);
+                assert_eq!(lazy.try_root(), tree.try_root(), "{:?}", context);
+
+                for index in 0..=tree.len() {
+                    assert_eq!(lazy.proof(index), tree.proof(index), "{:?} at {}", context, index);
+                    assert_eq!(lazy.leaf(index), tree.leaf(index), "{:?} at {}", context, index);
+                }
+                for level in 0..=tree.height() {
+                    for index in 0..=tree.len() {
+                        assert_eq!(lazy.node(level, index), tree.node(level, index));
+                    }
+                }
+                assert_eq!(lazy.contains(size / 2), tree.contains(size / 2));
+
+                tree.append(size);
+                lazy.append(size);
+            }
+
+            // Removing leaves keeps the two in step as well
+            tree.truncate(21);
+            lazy.truncate(21);
+            assert_eq!(lazy.root(), tree.root());
+            assert_eq!(lazy.pop(), tree.pop());
+            assert_eq!(lazy.proof(7), tree.proof(7));
+            assert_eq!(lazy.into_tree(), tree);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+/*
+    Building a simple merkle tree structure, starting with append-only
+
+    - using SHA-256 hash fucntion
+    - key will be u64
+    - leaves will be hash of key
+    - Hash will be represented as 32-byte fixed-size hash value.  
+        - 256 = 32 * 8, 32 bytes
+        - keys will be u64, so 8 * 8 bytes.  
+        - Hashing a key will be: Hash = SHA256(0x00 || 8 bytes of key)
+        - will result in a 32-byte hash value.
+    
+
+    type Hash = 32-byte fix-sized hash value
+
+    fn hash_key(key: u64) -> Hash
+        - returns Hash = H(0x00 || serialized_key)
+
+    fn hash_internal(left: Hash, right: Hash) -> Hash
+        - concatenates left and right (left || right)
+        - returns Hash = H(0x01 || left || right)
+
+    Domain separation:
+        - leaves are prefixed with 0x00 and internal nodes with 0x01 before hashing.
+        - without it, an internal node H(left || right) could be presented as a leaf
+          whose data is (left || right), the classic second-preimage attack.
+        - this changed every root value compared to the unprefixed scheme.
+
+
+    Will store nodes added to the tree inside a vector of vectors: levels = Vec<Vec<Hash>>;
+        - leaves will always be at levels[0] because the tree grows upward.  In regular Merkle trees, you are not concerned
+        with the position in the tree based on the binary path.  Leaves determine structure based solely on their order
+        - For example:
+            key1 = 4
+            key2 = 8
+            key3 = 10
+        levels[0] = [ H(4), H(8), H(10) ]
+
+            - Here, you would compute the hash H( H(4) || H(8) ) to get parent 1 and H( H(10) || H(10) ) to get parent 2.
+            - Then the two parents would be hashed together to get the root node
+
+
+    Design:
+
+    struct MerkleTree<D: Digest = Sha256, const N: usize = 32> {
+        levels: Vec<Vec<Hash<N>>>
+    }
+
+    - D is the digest used for every hash in the tree, SHA-256 by default.
+    - Hash<N> = [u8; N], holding exactly the first N bytes of each digest output.
+        - MerkleTree<Sha512, 64> keeps full SHA-512 hashes.
+        - MerkleTree<Sha256, 20> truncates SHA-256 to 20 bytes.
+        - N larger than the digest output is a compile error.
+    - Sha256Tree = MerkleTree<Sha256, 32> names the default configuration.
+
+    Portability:
+        - keys are hashed as fixed big-endian (or little-endian) u64 bytes, never usize,
+          so a tree has the same root on 32-bit and 64-bit targets (test_vectors pins them).
+        - index math works on usize in memory only.  Every byte format writes its counts
+          as fixed-width big-endian integers (u32 proof step counts, u64 leaf counts), so
+          a proof or file made on a 64-bit server decodes the same on a 32-bit WASM client.
+
+    Features:
+        - std (default): file I/O (save/load), set mode and SharedTree.  Without it the crate is
+          no_std and only needs alloc for its Vec levels and String hex output.
+        - serde: Serialize/Deserialize for MerkleTree, hashes as hex strings, and to_json.
+        - rayon: parallel construction and batch proof verification, implies std.
+        - metrics: rebuild_count(), counting how often the upper levels were recomputed.
+
+    Core methods:
+    - new() -> MerkleTree
+        - Creates an empty tree
+        - levels starts empty, no leaves or root.
+
+    - append<K: MerkleKey>(key: K)
+        - hashes the key's leaf bytes to get a leaf hash, hash_key(key) for a u64 key
+        - appends leaf hash to levels[0], or create levels[0] if it doesn't exist
+        - rebuilding upper levels by
+            - hashing together leaves in pairs, duplicating the last leaf if the length of that level is odd.
+            - using hash_internal method
+        - only the last node of each level covers the new leaf, so only that right edge
+          is recomputed: O(log n) per append.
+
+    - with_policy(policy: OddNodePolicy) -> MerkleTree
+        - same as new(), but picks how the last node of an odd-length level is handled:
+          Duplicate (default, H(node || node)) or Promote (node moves up unchanged).
+
+    - root() -> Option<Hash>
+        - returns the root hash if it exists, otherwise returns none. 
+
+    - try_root() -> Result<Hash, MerkleError>
+        - same as root(), but an empty tree is reported as MerkleError::Empty.
+
+*/
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod bounded;
+mod commitment;
+mod consistency;
+mod dot;
+mod error;
+mod fixed;
+mod frontier;
+mod hasher;
+mod hashing;
+mod key;
+mod lazy;
+mod multiproof;
+mod mutable;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod proof;
+#[cfg(feature = "serde")]
+mod serde_hex;
+#[cfg(feature = "std")]
+mod set;
+#[cfg(feature = "std")]
+mod shared;
+mod sorted;
+mod sparse;
+#[cfg(feature = "std")]
+mod storage;
+mod stats;
+mod tree;
+mod vectors;
+
+pub use sha2::digest::Digest;
+
+pub use bounded::BoundedTree;
+pub use commitment::{COMMITMENT_VERSION, RootCommitment};
+pub use consistency::{
+    Snapshot, verify_append, verify_append_with, verify_consistency, verify_consistency_with,
+};
+pub use error::{CommitmentError, HexError, MerkleError, ProofError};
+pub use fixed::FixedDepthTree;
+pub use frontier::{
+    bag_peaks, bag_peaks_with, root_from_frontier, root_from_frontier_with, verify_mmr_proof,
+    verify_mmr_proof_with,
+};
+pub use hasher::{Hasher, LeafHasher, NodeHasher};
+pub use hashing::{
+    Endianness, Hash, hash_from_hex, hash_from_hex_with, hash_internal, hash_internal_with,
+    hash_key, hash_key_in_domain, hash_key_in_domain_with, hash_key_with, hash_leaf_bytes,
+    hash_leaf_bytes_with, hash_to_hex, root_hamming_distance,
+};
+pub use key::MerkleKey;
+pub use lazy::LazyTree;
+pub use multiproof::{MultiProof, verify_multi_proof, verify_multi_proof_with};
+pub use mutable::MutableTree;
+pub use proof::{
+    Proof, proof_root, proof_root_with, verify_and_index, verify_and_index_with, verify_batch,
+    verify_batch_with, verify_compact, verify_proof, verify_proof_indexed,
+    verify_proof_indexed_with, verify_proof_iter, verify_proof_iter_with, verify_proof_with,
+};
+#[cfg(feature = "rayon")]
+pub use parallel::{verify_batch_parallel, verify_batch_parallel_with};
+#[cfg(feature = "std")]
+pub use set::MerkleSet;
+#[cfg(feature = "std")]
+pub use shared::SharedTree;
+#[cfg(feature = "std")]
+pub use storage::{read_proof, write_proof};
+pub use sorted::{AbsenceProof, Neighbor, SortedTree, verify_absence, verify_absence_with};
+pub use sparse::{
+    SPARSE_DEPTH, SparseMerkleTree, verify_non_membership, verify_non_membership_with,
+    verify_sparse_proof, verify_sparse_proof_with,
+};
+pub use stats::TreeStats;
+pub use tree::{MerkleTree, OddNodePolicy, Sha256Tree, root_of, root_of_with};
+pub use vectors::{TestVector, golden_roots, leaves_from_keys, test_vectors};
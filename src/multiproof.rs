@@ -0,0 +1,272 @@
+/*
+    Multi-proofs
+
+    A multi-proof covers several leaves at once.  Walking up level by level, a node
+    whose sibling is also on one of the covered paths doesn't need that sibling in the
+    proof, since the verifier computes it anyway.  Only the siblings no covered path
+    reaches are included, once each.
+
+    Proof layout:
+        - indices: the covered leaf indices, sorted and without repeats
+        - tree_size: the number of leaves in the tree
+        - hashes: the missing siblings, level by level from the leaves up,
+          left to right within a level
+*/
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+
+use sha2::Sha256;
+use sha2::digest::Digest;
+
+use crate::hashing::{Hash, ct_eq, hash_internal_with, hash_to_hex};
+use crate::tree::{MerkleTree, OddNodePolicy, level_width};
+
+/// A single proof for several leaves of the same tree.
+pub struct MultiProof<D: Digest = Sha256, const N: usize = 32> {
+    /// Covered leaf indices, sorted and without repeats.
+    pub indices: Vec<usize>,
+    /// Number of leaves in the tree the proof was made from.
+    pub tree_size: usize,
+    /// Sibling hashes not derivable from the covered leaves.
+    pub hashes: Vec<Hash<N>>,
+    digest: PhantomData<D>,
+}
+
+impl<D: Digest, const N: usize> MultiProof<D, N> {
+    /// Assembles a multi-proof from its parts, e.g. after receiving it over the wire.
+    pub fn new(indices: Vec<usize>, tree_size: usize, hashes: Vec<Hash<N>>) -> Self {
+        MultiProof {
+            indices,
+            tree_size,
+            hashes,
+            digest: PhantomData,
+        }
+    }
+}
+
+// Implemented by hand so that D itself doesn't need Clone or Debug.
+impl<D: Digest, const N: usize> Clone for MultiProof<D, N> {
+    fn clone(&self) -> Self {
+        MultiProof::new(self.indices.clone(), self.tree_size, self.hashes.clone())
+    }
+}
+
+impl<D: Digest, const N: usize> fmt::Debug for MultiProof<D, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex_hashes: Vec<String> = self.hashes.iter().map(|hash| hash_to_hex(hash)).collect();
+
+        f.debug_struct("MultiProof")
+            .field("indices", &self.indices)
+            .field("tree_size", &self.tree_size)
+            .field("hashes", &hex_hashes)
+            .finish()
+    }
+}
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Returns one proof covering every leaf in `indices`, sharing siblings between paths.
+    /// Returns None if `indices` is empty or any index is out of range.
+    pub fn multi_proof(&self, indices: &[usize]) -> Option<MultiProof<D, N>> {
+        let tree_size = self.len();
+        if indices.is_empty() || indices.iter().any(|&index| index >= tree_size) {
+            return None;
+        }
+
+        // Work on sorted, unique positions
+        let mut known: Vec<usize> = indices.to_vec();
+        known.sort_unstable();
+        known.dedup();
+        let covered = known.clone();
+
+        let mut hashes = Vec::new();
+
+        // Walk every level except the root level
+        for level in &self.levels[..self.levels.len() - 1] {
+            let mut i = 0;
+            while i < known.len() {
+                let node = known[i];
+                let sibling = node ^ 1;
+
+                if i + 1 < known.len() && known[i + 1] == sibling {
+                    // Both children are known, nothing to add
+                    i += 2;
+                    continue;
+                }
+
+                // A lone last node has no sibling to send, the policy derives its parent
+                if sibling < level.len() {
+                    hashes.push(level[sibling]);
+                }
+                i += 1;
+            }
+
+            // Move up to the parents' positions
+            known = known.iter().map(|node| node / 2).collect();
+            known.dedup();
+        }
+
+        Some(MultiProof::new(covered, tree_size, hashes))
+    }
+}
+
+/// Checks that `leaves` (in the order of `proof.indices`) hash up to `root`.
+/// Expects a default SHA-256 tree with duplicated odd nodes.
+pub fn verify_multi_proof(leaves: &[Hash], proof: &MultiProof, root: Hash) -> bool {
+    verify_multi_proof_with::<Sha256, 32>(OddNodePolicy::Duplicate, leaves, proof, root)
+}
+
+/// Same as `verify_multi_proof`, for a tree hashed with the digest `D` using `policy`.
+pub fn verify_multi_proof_with<D: Digest, const N: usize>(
+    policy: OddNodePolicy,
+    leaves: &[Hash<N>],
+    proof: &MultiProof<D, N>,
+    root: Hash<N>,
+) -> bool {
+    // One leaf per index, indices strictly increasing and inside the tree
+    if leaves.is_empty() || leaves.len() != proof.indices.len() {
+        return false;
+    }
+    if proof.indices.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return false;
+    }
+    if proof.indices.last().is_none_or(|&last| last >= proof.tree_size) {
+        return false;
+    }
+
+    // Known nodes on the current level as (position, hash), sorted by position
+    let mut known: Vec<(usize, Hash<N>)> = proof
+        .indices
+        .iter()
+        .copied()
+        .zip(leaves.iter().cloned())
+        .collect();
+    let mut hashes = proof.hashes.iter();
+    let mut level = 0;
+
+    while level_width(proof.tree_size, level) > 1 {
+        let width = level_width(proof.tree_size, level);
+        let mut parents = Vec::with_capacity(known.len());
+
+        let mut i = 0;
+        while i < known.len() {
+            let (node, hash) = &known[i];
+            let sibling = node ^ 1;
+
+            let parent = if i + 1 < known.len() && known[i + 1].0 == sibling {
+                // Both children known, the left one comes first
+                i += 1;
+                hash_internal_with::<D, N>(*hash, known[i].1)
+            } else if sibling >= width {
+                // Lone last node
+                policy.lone_parent::<D, N>(hash)
+            } else {
+                // Sibling comes from the proof
+                let Some(sibling_hash) = hashes.next() else {
+                    return false;
+                };
+                if node.is_multiple_of(2) {
+                    hash_internal_with::<D, N>(*hash, *sibling_hash)
+                } else {
+                    hash_internal_with::<D, N>(*sibling_hash, *hash)
+                }
+            };
+
+            parents.push((node / 2, parent));
+            i += 1;
+        }
+
+        known = parents;
+        level += 1;
+    }
+
+    // Every proof hash must have been used, and the walk must end at the root
+    hashes.next().is_none() && known.len() == 1 && ct_eq(&known[0].1, &root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use crate::hashing::hash_key;
+    use crate::tree::Sha256Tree;
+
+    /// An 11-leaf tree over keys 0..11, with odd levels of 11 and 3 nodes.
+    fn tree(policy: OddNodePolicy) -> Sha256Tree {
+        let mut tree = Sha256Tree::with_policy(policy);
+        tree.append_many(&(0..11).collect::<Vec<_>>());
+        tree
+    }
+
+    fn leaves(indices: &[usize]) -> Vec<Hash> {
+        indices.iter().map(|&index| hash_key(index as u64)).collect()
+    }
+
+    #[test]
+    fn unsorted_and_duplicate_indices_are_normalized() {
+        let tree = tree(OddNodePolicy::Duplicate);
+        let root = tree.root().unwrap();
+
+        let proof = tree.multi_proof(&[7, 2, 7, 3, 10, 2]).unwrap();
+        assert_eq!(proof.indices, vec![2, 3, 7, 10]);
+        assert_eq!(proof.tree_size, 11);
+        assert!(verify_multi_proof(&leaves(&proof.indices), &proof, root));
+
+        // Covering every leaf leaves nothing to send
+        let all: Vec<usize> = (0..11).rev().collect();
+        let proof = tree.multi_proof(&all).unwrap();
+        assert!(proof.hashes.is_empty());
+        assert!(verify_multi_proof(&leaves(&proof.indices), &proof, root));
+
+        assert!(tree.multi_proof(&[]).is_none());
+        assert!(tree.multi_proof(&[1, 11]).is_none());
+    }
+
+    #[test]
+    fn odd_levels_verify_under_both_policies() {
+        for policy in [OddNodePolicy::Duplicate, OddNodePolicy::Promote] {
+            let tree = tree(policy);
+            let root = tree.root().unwrap();
+
+            // The last leaf is the lone node of the leaf level
+            for indices in [&[10][..], &[0, 10], &[4, 5, 9], &[1, 6, 8, 10]] {
+                let proof = tree.multi_proof(indices).unwrap();
+                let verified = verify_multi_proof_with::<Sha256, 32>(
+                    policy,
+                    &leaves(&proof.indices),
+                    &proof,
+                    root,
+                );
+                assert!(verified, "{:?} for {:?}", policy, indices);
+            }
+        }
+    }
+
+    #[test]
+    fn wrong_leaves_are_rejected() {
+        let tree = tree(OddNodePolicy::Duplicate);
+        let root = tree.root().unwrap();
+        let proof = tree.multi_proof(&[2, 7, 10]).unwrap();
+
+        let mut wrong = leaves(&proof.indices);
+        wrong[1] = hash_key(99);
+        assert!(!verify_multi_proof(&wrong, &proof, root));
+
+        // Right leaves in the wrong order, or one leaf missing
+        let mut swapped = leaves(&proof.indices);
+        swapped.swap(0, 1);
+        assert!(!verify_multi_proof(&swapped, &proof, root));
+        assert!(!verify_multi_proof(&leaves(&[2, 7]), &proof, root));
+
+        // Nor does checking it under the other policy
+        let promoted = verify_multi_proof_with::<Sha256, 32>(
+            OddNodePolicy::Promote,
+            &leaves(&proof.indices),
+            &proof,
+            root,
+        );
+        assert!(!promoted);
+    }
+}
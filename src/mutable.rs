@@ -0,0 +1,150 @@
+/*
+    Mutable mode
+
+    A regular tree is append-only: once a leaf is logged it never changes, which is
+    what inclusion and consistency proofs rely on.  MutableTree wraps a tree for
+    state commitments instead, where a leaf is a slot whose value can be overwritten.
+
+    Overwriting a leaf only changes its ancestors, so update recomputes that one path
+    to the root: O(log n).  Consistency proofs between two states of a MutableTree
+    are meaningless, since old leaves may have changed.
+
+    Read-only tree methods (root, proof, len, ...) are available through Deref.
+*/
+
+use core::ops::Deref;
+
+use sha2::Sha256;
+use sha2::digest::Digest;
+
+use crate::error::MerkleError;
+use crate::hashing::{Hash, Key};
+use crate::key::MerkleKey;
+use crate::tree::MerkleTree;
+
+/// A Merkle tree whose leaves can be overwritten in place.
+pub struct MutableTree<D: Digest = Sha256, const N: usize = 32> {
+    tree: MerkleTree<D, N>,
+}
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Creates an empty tree in mutable mode, where leaves can be updated after appending.
+    pub fn new_mutable() -> MutableTree<D, N> {
+        MerkleTree::new().into_mutable()
+    }
+
+    /// Wraps this tree in mutable mode, keeping its leaves and policy.
+    pub fn into_mutable(self) -> MutableTree<D, N> {
+        MutableTree { tree: self }
+    }
+}
+
+impl<D: Digest, const N: usize> MutableTree<D, N> {
+    /// Appends a new key as a leaf, same as `MerkleTree::append`.
+    pub fn append(&mut self, key: Key) {
+        self.tree.append(key);
+    }
+
+    /// Appends a key of any `MerkleKey` type, same as `MerkleTree::append_key`.
+    pub fn append_key<K: MerkleKey>(&mut self, key: K) {
+        self.tree.append_key(key);
+    }
+
+    /// Replaces the leaf at `index` with the hash of `key` and recomputes its path to the root.
+    /// Returns MerkleError::IndexOutOfRange if there is no such leaf; the tree is unchanged on error.
+    pub fn update<K: MerkleKey>(&mut self, index: usize, key: K) -> Result<(), MerkleError> {
+        // Check before hashing, so a failed update doesn't fill the key cache either
+        self.check_index(index)?;
+        let leaf = self.tree.key_leaf_cached(&key);
+        self.update_hash(index, leaf)
+    }
+
+    /// Same as `update`, with an already-computed leaf hash.
+    pub fn update_hash(&mut self, index: usize, leaf: Hash<N>) -> Result<(), MerkleError> {
+        self.check_index(index)?;
+
+        self.tree.levels[0][index] = leaf;
+        self.tree.rebuild_path(index);
+        Ok(())
+    }
+
+    /// Unwraps the underlying tree.
+    pub fn into_tree(self) -> MerkleTree<D, N> {
+        self.tree
+    }
+
+    /// Fails unless there is a leaf at `index`.
+    fn check_index(&self, index: usize) -> Result<(), MerkleError> {
+        let len = self.tree.len();
+        if index >= len {
+            return Err(MerkleError::IndexOutOfRange { index, len });
+        }
+        Ok(())
+    }
+}
+
+impl<D: Digest, const N: usize> Deref for MutableTree<D, N> {
+    type Target = MerkleTree<D, N>;
+
+    fn deref(&self) -> &MerkleTree<D, N> {
+        &self.tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{OddNodePolicy, Sha256Tree};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn update_matches_rebuilding_from_edited_keys() {
+        for policy in [OddNodePolicy::Duplicate, OddNodePolicy::Promote] {
+            for size in 1..=17u64 {
+                let mut keys: Vec<u64> = (0..size).collect();
+                let mut tree = Sha256Tree::with_policy(policy).into_mutable();
+                tree.tree.append_many(&keys);
+
+                // Overwrite the first, the last and a middle leaf, in that order
+                for index in [0, size as usize - 1, size as usize / 2] {
+                    let key = 1000 + index as u64;
+                    keys[index] = key;
+                    tree.update(index, key).unwrap();
+
+                    let mut rebuilt = Sha256Tree::with_policy(policy);
+                    rebuilt.append_many(&keys);
+                    assert_eq!(tree.root(), rebuilt.root(), "{:?}", (policy, size, index));
+                    assert_eq!(*tree, rebuilt, "{:?}", (policy, size, index));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn failed_update_changes_nothing() {
+        let mut tree = Sha256Tree::new_mutable();
+        for key in 0..5u64 {
+            tree.append(key);
+        }
+        let root = tree.root();
+
+        let error = MerkleError::IndexOutOfRange { index: 5, len: 5 };
+        assert_eq!(tree.update(5, 99u64), Err(error.clone()));
+        assert_eq!(tree.update_hash(5, [0; 32]), Err(error));
+        assert_eq!(tree.root(), root);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn failed_update_leaves_the_key_cache_alone() {
+        let mut tree = Sha256Tree::new_cached().into_mutable();
+        tree.append(1u64);
+        assert_eq!(tree.cached_keys(), 1);
+
+        assert!(tree.update(1, 2u64).is_err());
+        assert_eq!(tree.cached_keys(), 1);
+
+        tree.update(0, 2u64).unwrap();
+        assert_eq!(tree.cached_keys(), 2);
+    }
+}
@@ -0,0 +1,70 @@
+/*
+    Parallel construction (rayon feature)
+
+    Every hash on a level only depends on the level below, so both the leaf
+    hashing and each level's pairwise hashing split cleanly across threads.
+    The levels themselves are still built one after another, bottom-up.
+
+    Independent proofs against the same root can likewise be checked on separate threads.
+*/
+
+use rayon::prelude::*;
+use sha2::Sha256;
+use sha2::digest::Digest;
+
+use crate::hashing::{Hash, Key, hash_internal_with, hash_leaf_bytes_with};
+use crate::proof::verify_proof_with;
+use crate::tree::MerkleTree;
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Same as `from_keys`, hashing leaves and each level's pairs in parallel.
+    /// Produces exactly the same tree as the sequential build.
+    pub fn from_keys_parallel(keys: &[Key]) -> Self {
+        let mut tree = MerkleTree::new();
+        if keys.is_empty() {
+            return tree;
+        }
+
+        // Hash all leaves in parallel
+        let endianness = tree.endianness;
+        let leaves: Vec<Hash<N>> = keys
+            .par_iter()
+            .map(|&key| hash_leaf_bytes_with::<D, N>(&endianness.key_bytes(key)))
+            .collect();
+        tree.levels.push(leaves);
+
+        let policy = tree.policy;
+
+        // Build each level from the one below until a single root is left
+        while let Some(below) = tree.levels.last().filter(|level| level.len() > 1) {
+            let next_level: Vec<Hash<N>> = below
+                .par_chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_internal_with::<D, N>(*left, *right),
+                    [lone] => policy.lone_parent::<D, N>(lone),
+                    _ => unreachable!("chunks of 2 are never empty"),
+                })
+                .collect();
+
+            tree.levels.push(next_level);
+        }
+
+        tree
+    }
+}
+
+/// Same as `verify_batch`, checking the proofs in parallel.
+/// Stops scheduling more work once any proof fails.
+pub fn verify_batch_parallel(items: &[(Hash, Vec<(Hash, bool)>)], root: Hash) -> bool {
+    verify_batch_parallel_with::<Sha256, 32>(items, root)
+}
+
+/// Same as `verify_batch_parallel`, for a tree hashed with the digest `D` into N-byte hashes.
+pub fn verify_batch_parallel_with<D: Digest, const N: usize>(
+    items: &[(Hash<N>, Vec<(Hash<N>, bool)>)],
+    root: Hash<N>,
+) -> bool {
+    items
+        .par_iter()
+        .all(|(leaf, proof)| verify_proof_with::<D, N>(*leaf, proof, root))
+}
@@ -0,0 +1,669 @@
+/*
+    Inclusion proofs
+
+    A proof for the leaf at levels[0][index] is the list of sibling hashes met
+    while walking from that leaf up to the root.  Each step is (sibling, is_right):
+        - is_right == true  → sibling sits to the right, parent = H(node || sibling)
+        - is_right == false → sibling sits to the left,  parent = H(sibling || node)
+
+    When a level has odd length, the lone last node follows the tree's OddNodePolicy:
+        - Duplicate → the node is its own sibling (always on the right)
+        - Promote   → the node moves up unchanged, so that level adds no step
+
+    Proof wraps a path for transport.  Byte layout (integers big-endian):
+        - step count: u32
+        - each step:  N hash bytes (32 for SHA-256), then a direction byte (1 = right, 0 = left)
+
+    The directions follow from the leaf index and tree size, so a verifier that knows both
+    can check a bare sibling list with verify_proof_indexed instead.
+
+    Every verifier compares the rebuilt root to the expected one in constant time.
+*/
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+
+use sha2::Sha256;
+use sha2::digest::Digest;
+
+use crate::error::{MerkleError, ProofError};
+use crate::hashing::{Hash, ct_eq, hash_internal_with, hash_to_hex};
+use crate::tree::{MerkleTree, OddNodePolicy, height_for, level_width};
+
+/// Size of the step-count prefix in the byte format.
+const COUNT_LEN: usize = 4;
+
+/// An inclusion proof: the (sibling, is_right) steps from a leaf up to the root.
+pub struct Proof<D: Digest = Sha256, const N: usize = 32> {
+    steps: Vec<(Hash<N>, bool)>,
+    digest: PhantomData<D>,
+}
+
+impl<D: Digest, const N: usize> Proof<D, N> {
+    /// Wraps a path as returned by `MerkleTree::proof`.
+    pub fn new(steps: Vec<(Hash<N>, bool)>) -> Self {
+        Proof {
+            steps,
+            digest: PhantomData,
+        }
+    }
+
+    /// Returns the (sibling, is_right) steps, leaf side first.
+    pub fn steps(&self) -> &[(Hash<N>, bool)] {
+        &self.steps
+    }
+
+    /// Unwraps the steps.
+    pub fn into_steps(self) -> Vec<(Hash<N>, bool)> {
+        self.steps
+    }
+
+    /// Returns the number of sibling steps.
+    pub fn depth(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Returns the length of `to_bytes()` without encoding anything.
+    pub fn byte_len(&self) -> usize {
+        COUNT_LEN + self.steps.len() * (N + 1)
+    }
+
+    /// Encodes the proof as a step count followed by each hash and direction byte.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.byte_len());
+
+        bytes.extend_from_slice(&(self.steps.len() as u32).to_be_bytes());
+        for (sibling, is_right) in &self.steps {
+            bytes.extend_from_slice(sibling);
+            bytes.push(u8::from(*is_right));
+        }
+
+        bytes
+    }
+
+    /// Decodes bytes written by `to_bytes`.  Wrong lengths and bad direction
+    /// bytes are reported as errors, never panics.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProofError> {
+        let step_len = N + 1;
+
+        // Read the step count
+        let Some((count, mut rest)) = bytes.split_first_chunk::<COUNT_LEN>() else {
+            return Err(ProofError::Truncated { expected: COUNT_LEN, found: bytes.len() });
+        };
+        let count = u32::from_be_bytes(*count) as usize;
+
+        // Check the total length before allocating anything
+        let expected = count
+            .checked_mul(step_len)
+            .and_then(|len| len.checked_add(COUNT_LEN))
+            .unwrap_or(usize::MAX);
+        if bytes.len() < expected {
+            return Err(ProofError::Truncated { expected, found: bytes.len() });
+        }
+        if bytes.len() > expected {
+            return Err(ProofError::TrailingBytes { expected, found: bytes.len() });
+        }
+
+        let mut steps = Vec::with_capacity(count);
+        for step in 0..count {
+            let (hash, tail) = rest.split_first_chunk::<N>().expect("length was checked above");
+            let is_right = match tail[0] {
+                0 => false,
+                1 => true,
+                found => return Err(ProofError::InvalidDirection { step, found }),
+            };
+            steps.push((*hash, is_right));
+            rest = &tail[1..];
+        }
+
+        Ok(Proof::new(steps))
+    }
+
+    /// Patches this proof for the leaf at `my_index`, made before the last append to `tree`,
+    /// so it verifies against the new root.  Siblings covering subtrees that were already
+    /// complete keep their hashes; only those on the old right edge, and any step the new
+    /// leaf adds, are read from `tree`.  MerkleError::IndexOutOfRange if `my_index` wasn't
+    /// a leaf before the append.
+    pub fn update_after_append(
+        &mut self,
+        tree: &MerkleTree<D, N>,
+        my_index: usize,
+    ) -> Result<(), MerkleError> {
+        let new_size = tree.len();
+        let old_size = new_size.saturating_sub(1);
+        if my_index >= old_size {
+            return Err(MerkleError::IndexOutOfRange { index: my_index, len: old_size });
+        }
+
+        let old_height = height_for(old_size);
+        let mut old_steps = self.steps.iter();
+        let mut steps = Vec::with_capacity(height_for(new_size) - 1);
+
+        for level in 0..height_for(new_size) - 1 {
+            let node_index = my_index >> level;
+
+            // The step the old tree had at this level, if any
+            let old_step = if level < old_height - 1
+                && tree.has_step(node_index, level_width(old_size, level))
+            {
+                old_steps.next()
+            } else {
+                None
+            };
+
+            // A sibling whose subtree was complete before the append can't have changed
+            let sibling = |index: usize| {
+                let end = (index + 1).checked_shl(level as u32).unwrap_or(usize::MAX);
+                match old_step {
+                    Some((hash, _)) if end <= old_size => *hash,
+                    _ => tree.levels[level][index],
+                }
+            };
+            steps.extend(tree.step_with(node_index, level_width(new_size, level), sibling));
+        }
+
+        self.steps = steps;
+        Ok(())
+    }
+}
+
+impl<D: Digest, const N: usize> From<Vec<(Hash<N>, bool)>> for Proof<D, N> {
+    fn from(steps: Vec<(Hash<N>, bool)>) -> Self {
+        Proof::new(steps)
+    }
+}
+
+// Implemented by hand so that D itself doesn't need Clone, PartialEq or Debug.
+impl<D: Digest, const N: usize> Clone for Proof<D, N> {
+    fn clone(&self) -> Self {
+        Proof::new(self.steps.clone())
+    }
+}
+
+impl<D: Digest, const N: usize> PartialEq for Proof<D, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.steps == other.steps
+    }
+}
+
+impl<D: Digest, const N: usize> Eq for Proof<D, N> {}
+
+impl<D: Digest, const N: usize> fmt::Debug for Proof<D, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex_steps: Vec<(String, bool)> = self
+            .steps
+            .iter()
+            .map(|(sibling, is_right)| (hash_to_hex(sibling), *is_right))
+            .collect();
+
+        f.debug_struct("Proof").field("steps", &hex_steps).finish()
+    }
+}
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Returns the sibling path from the leaf at `index` up to the root,
+    /// or None if `index` is out of range.
+    pub fn proof(&self, index: usize) -> Option<Vec<(Hash<N>, bool)>> {
+        // Out of range (including the empty tree) → no proof
+        let leaves = self.levels.first()?;
+        if index >= leaves.len() {
+            return None;
+        }
+
+        let mut path = Vec::new();
+        let mut node_index = index;
+
+        // Walk every level except the root level
+        for level in 0..self.levels.len() - 1 {
+            path.extend(self.proof_step(level, node_index));
+
+            // Move up to the parent's position
+            node_index /= 2;
+        }
+
+        Some(path)
+    }
+
+    /// Returns how many steps `proof(index)` would have, without building it, or None if
+    /// `index` is out of range.  With Duplicate that is always the height minus one; with
+    /// Promote, every level where the node is a lone last one adds no step.
+    pub fn proof_len(&self, index: usize) -> Option<usize> {
+        let len = self.len();
+        if index >= len {
+            return None;
+        }
+
+        let steps = (0..self.levels.len() - 1)
+            .filter(|&level| self.has_step(index >> level, level_width(len, level)))
+            .count();
+
+        Some(steps)
+    }
+
+    /// Returns the sibling hashes of `proof(index)` without their directions, one byte per
+    /// step smaller.  The directions follow from the index and tree size, so check it with
+    /// `verify_compact` (or `verify_proof_indexed_with` for other policies and digests).
+    /// None if `index` is out of range.
+    pub fn compact_proof(&self, index: usize) -> Option<Vec<Hash<N>>> {
+        let path = self.proof(index)?;
+        Some(path.into_iter().map(|(sibling, _)| sibling).collect())
+    }
+
+    /// Returns the sibling path for the leaf at `index` in the tree as it was with exactly
+    /// `size` leaves, to verify against `root_at(size)`.  None if `index >= size`
+    /// or `size` is larger than the current tree.
+    pub fn proof_at(&self, index: usize, size: usize) -> Option<Vec<(Hash<N>, bool)>> {
+        if index >= size || size > self.len() {
+            return None;
+        }
+
+        let mut path = Vec::new();
+        let mut node_index = index;
+
+        // Same walk as proof, over the levels of the smaller tree
+        for level in 0..height_for(size) - 1 {
+            let width = level_width(size, level);
+            path.extend(self.step_with(node_index, width, |i| self.node_at(level, i, size)));
+
+            // Move up to the parent's position
+            node_index /= 2;
+        }
+
+        Some(path)
+    }
+
+    /// The hash of node (level, index) in the tree of `size` leaves.
+    /// Complete subtrees are unchanged by later appends and read as stored, only nodes
+    /// on that tree's right edge are rehashed.
+    fn node_at(&self, level: usize, index: usize, size: usize) -> Hash<N> {
+        let end = (index + 1).checked_shl(level as u32).unwrap_or(usize::MAX);
+        if end <= size {
+            return self.levels[level][index];
+        }
+
+        let left = self.node_at(level - 1, 2 * index, size);
+        if 2 * index + 1 < level_width(size, level - 1) {
+            self.hash_node(left, self.node_at(level - 1, 2 * index + 1, size))
+        } else {
+            self.lone_parent(&left)
+        }
+    }
+
+    /// Returns the proof of every leaf, in leaf order.  Each step is looked up once per
+    /// node and shared by all the leaves under it.
+    pub fn all_proofs(&self) -> Vec<Vec<(Hash<N>, bool)>> {
+        let len = self.len();
+        let depth = self.height().saturating_sub(1);
+        let mut proofs = vec![Vec::with_capacity(depth); len];
+
+        for level in 0..depth {
+            for (node_index, leaves) in proofs.chunks_mut(1 << level).enumerate() {
+                let Some(step) = self.proof_step(level, node_index) else {
+                    continue;
+                };
+                for proof in leaves {
+                    proof.push(step);
+                }
+            }
+        }
+
+        proofs
+    }
+
+    /// The proof step contributed by the node at (level, node_index), if any.
+    fn proof_step(&self, level: usize, node_index: usize) -> Option<(Hash<N>, bool)> {
+        let nodes = &self.levels[level];
+        self.step_with(node_index, nodes.len(), |i| nodes[i])
+    }
+
+    /// Whether the node at `node_index` on a level of `width` nodes adds a proof step,
+    /// false only for a lone last node that Promote moves up unchanged.
+    fn has_step(&self, node_index: usize, width: usize) -> bool {
+        let lone = node_index.is_multiple_of(2) && node_index + 1 >= width;
+        !(lone && self.policy == OddNodePolicy::Promote)
+    }
+
+    /// The proof step for `node_index` on a level of `width` nodes, looking hashes up with `node`.
+    fn step_with(
+        &self,
+        node_index: usize,
+        width: usize,
+        node: impl Fn(usize) -> Hash<N>,
+    ) -> Option<(Hash<N>, bool)> {
+        if node_index.is_multiple_of(2) {
+            // Left child: sibling is to the right, or the node itself if it was duplicated
+            if node_index + 1 < width {
+                Some((node(node_index + 1), true))
+            } else if self.policy == OddNodePolicy::Duplicate {
+                Some((node(node_index), true))
+            } else {
+                None
+            }
+        } else {
+            // Right child: sibling is always the node to the left
+            Some((node(node_index - 1), false))
+        }
+    }
+}
+
+/// Checks that `leaf` hashes up to `expected_root` along `proof`.
+/// An empty proof means a single-leaf tree, where the leaf is the root.
+pub fn verify_proof(leaf: Hash, proof: &[(Hash, bool)], expected_root: Hash) -> bool {
+    verify_proof_with::<Sha256, 32>(leaf, proof, expected_root)
+}
+
+/// Same as `verify_proof`, for a tree hashed with the digest `D` into N-byte hashes.
+pub fn verify_proof_with<D: Digest, const N: usize>(
+    leaf: Hash<N>,
+    proof: &[(Hash<N>, bool)],
+    expected_root: Hash<N>,
+) -> bool {
+    verify_proof_iter_with::<D, N>(leaf, proof.iter().copied(), expected_root)
+}
+
+/// Same as `verify_proof`, taking the (sibling, is_right) steps from any iterator,
+/// e.g. straight off a stream, so the proof never has to be collected.
+pub fn verify_proof_iter<I: IntoIterator<Item = (Hash, bool)>>(
+    leaf: Hash,
+    steps: I,
+    expected_root: Hash,
+) -> bool {
+    verify_proof_iter_with::<Sha256, 32>(leaf, steps, expected_root)
+}
+
+/// Same as `verify_proof_iter`, for a tree hashed with the digest `D` into N-byte hashes.
+pub fn verify_proof_iter_with<D: Digest, const N: usize>(
+    leaf: Hash<N>,
+    steps: impl IntoIterator<Item = (Hash<N>, bool)>,
+    expected_root: Hash<N>,
+) -> bool {
+    ct_eq(&fold_proof(leaf, steps, hash_internal_with::<D, N>), &expected_root)
+}
+
+/// Folds `proof` into `leaf` and returns the root it leads to, without comparing it.
+/// An empty proof returns the leaf unchanged.
+pub fn proof_root(leaf: Hash, proof: &[(Hash, bool)]) -> Hash {
+    proof_root_with::<Sha256, 32>(leaf, proof)
+}
+
+/// Same as `proof_root`, for a tree hashed with the digest `D` into N-byte hashes.
+pub fn proof_root_with<D: Digest, const N: usize>(
+    leaf: Hash<N>,
+    proof: &[(Hash<N>, bool)],
+) -> Hash<N> {
+    fold_proof(leaf, proof.iter().copied(), hash_internal_with::<D, N>)
+}
+
+/// Checks `proof` for a default SHA-256 tree of `tree_size` leaves with duplicated odd nodes,
+/// and returns the index of the leaf it proves, or None if it doesn't lead to `expected_root`.
+pub fn verify_and_index(
+    leaf: Hash,
+    proof: &[(Hash, bool)],
+    tree_size: usize,
+    expected_root: Hash,
+) -> Option<usize> {
+    verify_and_index_with::<Sha256, 32>(
+        OddNodePolicy::Duplicate,
+        leaf,
+        proof,
+        tree_size,
+        expected_root,
+    )
+}
+
+/// Same as `verify_and_index`, for a tree hashed with the digest `D` using `policy`.
+///
+/// The index is read from the directions top-down, where the tree size says which levels
+/// end in a lone node: a promoted one has no step, a duplicated one is a left child whatever
+/// its direction says.  It is then checked with `verify_proof_indexed_with`, which takes
+/// each side from the index, so a flipped direction can't name a leaf that doesn't exist.
+pub fn verify_and_index_with<D: Digest, const N: usize>(
+    policy: OddNodePolicy,
+    leaf: Hash<N>,
+    proof: &[(Hash<N>, bool)],
+    tree_size: usize,
+    expected_root: Hash<N>,
+) -> Option<usize> {
+    if tree_size == 0 {
+        return None;
+    }
+
+    // Walk down from the root, taking the steps from the root end
+    let mut steps = proof.iter().rev();
+    let mut index = 0;
+    for level in (0..height_for(tree_size) - 1).rev() {
+        let left = 2 * index;
+        let lone = left + 1 >= level_width(tree_size, level);
+
+        index = if lone {
+            if policy == OddNodePolicy::Duplicate {
+                steps.next()?;
+            }
+            left
+        } else {
+            // A sibling on the left means this node is a right child
+            let &(_, is_right) = steps.next()?;
+            if is_right { left } else { left + 1 }
+        };
+    }
+    if steps.next().is_some() {
+        return None;
+    }
+
+    let siblings: Vec<Hash<N>> = proof.iter().map(|(sibling, _)| *sibling).collect();
+    verify_proof_indexed_with::<D, N>(policy, leaf, index, tree_size, &siblings, expected_root)
+        .then_some(index)
+}
+
+/// Folds the `proof` steps into `leaf`, hashing each pair with `hash_node`.
+pub(crate) fn fold_proof<const N: usize>(
+    leaf: Hash<N>,
+    proof: impl IntoIterator<Item = (Hash<N>, bool)>,
+    hash_node: impl Fn(Hash<N>, Hash<N>) -> Hash<N>,
+) -> Hash<N> {
+    let mut current = leaf;
+
+    // Fold each sibling into the running hash, respecting which side it sits on
+    for (sibling, is_right) in proof {
+        current = if is_right {
+            hash_node(current, sibling)
+        } else {
+            hash_node(sibling, current)
+        };
+    }
+
+    current
+}
+
+/// Checks every (leaf, proof) pair against the same `root`, stopping at the first failure.
+/// Returns true only if all of them verify (and for an empty batch).
+pub fn verify_batch(items: &[(Hash, Vec<(Hash, bool)>)], root: Hash) -> bool {
+    verify_batch_with::<Sha256, 32>(items, root)
+}
+
+/// Same as `verify_batch`, for a tree hashed with the digest `D` into N-byte hashes.
+pub fn verify_batch_with<D: Digest, const N: usize>(
+    items: &[(Hash<N>, Vec<(Hash<N>, bool)>)],
+    root: Hash<N>,
+) -> bool {
+    items
+        .iter()
+        .all(|(leaf, proof)| verify_proof_with::<D, N>(*leaf, proof, root))
+}
+
+/// Checks that `leaf`, at `index` in a tree of `tree_size` leaves, hashes up to `root`
+/// along bare `siblings`.  The side of each sibling is derived from the index.
+/// Expects a default SHA-256 tree with duplicated odd nodes.
+pub fn verify_proof_indexed(
+    leaf: Hash,
+    index: usize,
+    tree_size: usize,
+    siblings: &[Hash],
+    root: Hash,
+) -> bool {
+    verify_proof_indexed_with::<Sha256, 32>(
+        OddNodePolicy::Duplicate,
+        leaf,
+        index,
+        tree_size,
+        siblings,
+        root,
+    )
+}
+
+/// Checks a proof from `compact_proof` for the leaf at `index` in a tree of `tree_size`
+/// leaves.  Same as `verify_proof_indexed`: a default SHA-256 tree with duplicated odd nodes.
+pub fn verify_compact(
+    leaf: Hash,
+    index: usize,
+    tree_size: usize,
+    siblings: &[Hash],
+    root: Hash,
+) -> bool {
+    verify_proof_indexed(leaf, index, tree_size, siblings, root)
+}
+
+/// Same as `verify_proof_indexed`, for a tree hashed with the digest `D` using `policy`.
+pub fn verify_proof_indexed_with<D: Digest, const N: usize>(
+    policy: OddNodePolicy,
+    leaf: Hash<N>,
+    index: usize,
+    tree_size: usize,
+    siblings: &[Hash<N>],
+    root: Hash<N>,
+) -> bool {
+    if index >= tree_size {
+        return false;
+    }
+
+    let mut current = leaf;
+    let mut node_index = index;
+    let mut width = tree_size;
+    let mut siblings = siblings.iter();
+
+    // Same walk as MerkleTree::proof, one level at a time until the root
+    while width > 1 {
+        let is_lone = node_index.is_multiple_of(2) && node_index + 1 == width;
+
+        // A promoted lone node moves up unchanged and has no sibling in the proof
+        if !(is_lone && policy == OddNodePolicy::Promote) {
+            let Some(sibling) = siblings.next() else {
+                return false;
+            };
+            current = if node_index.is_multiple_of(2) {
+                hash_internal_with::<D, N>(current, *sibling)
+            } else {
+                hash_internal_with::<D, N>(*sibling, current)
+            };
+        }
+
+        node_index /= 2;
+        width = width.div_ceil(2);
+    }
+
+    // Every sibling must have been used
+    siblings.next().is_none() && ct_eq(&current, &root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashing::hash_key;
+    use crate::tree::Sha256Tree;
+
+    fn tree_of(policy: OddNodePolicy, size: u64) -> Sha256Tree {
+        let mut tree = Sha256Tree::with_policy(policy);
+        for key in 0..size {
+            tree.append(key);
+        }
+        tree
+    }
+
+    #[test]
+    fn verify_and_index_returns_every_index() {
+        for policy in [OddNodePolicy::Duplicate, OddNodePolicy::Promote] {
+            for size in 1..40 {
+                let tree = tree_of(policy, size);
+                let root = tree.root().unwrap();
+                let len = tree.len();
+
+                for index in 0..len {
+                    let leaf = hash_key(index as u64);
+                    let proof = tree.proof(index).unwrap();
+                    let verify =
+                        |leaf| verify_and_index_with::<Sha256, 32>(policy, leaf, &proof, len, root);
+
+                    let context = (policy, size, index);
+                    assert_eq!(verify(leaf), Some(index), "{:?}", context);
+                    assert_eq!(verify(hash_key(size + 1)), None);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn verify_and_index_ignores_flipped_duplicate_step() {
+        // In a 3-leaf tree leaf 2 is duplicated, so H(c || c) doesn't depend on the direction
+        let tree = tree_of(OddNodePolicy::Duplicate, 3);
+        let mut proof = tree.proof(2).unwrap();
+        proof[0].1 = !proof[0].1;
+
+        let found = verify_and_index(hash_key(2), &proof, 3, tree.root().unwrap());
+        assert_eq!(found, Some(2));
+    }
+
+    #[test]
+    fn verify_and_index_finds_promoted_leaf() {
+        let mut tree = Sha256Tree::rfc6962();
+        for key in 0..5u64 {
+            tree.append(key);
+        }
+        let proof = tree.proof(4).unwrap();
+        let found = verify_and_index_with::<Sha256, 32>(
+            OddNodePolicy::Promote,
+            hash_key(4),
+            &proof,
+            5,
+            tree.root().unwrap(),
+        );
+
+        assert_eq!(found, Some(4));
+    }
+
+    #[test]
+    fn verify_and_index_rejects_wrong_size() {
+        let tree = tree_of(OddNodePolicy::Duplicate, 6);
+        let proof = tree.proof(5).unwrap();
+
+        assert_eq!(verify_and_index(hash_key(5), &proof, 0, tree.root().unwrap()), None);
+        assert_eq!(verify_and_index(hash_key(5), &proof, 4, tree.root().unwrap()), None);
+    }
+
+    #[test]
+    fn updated_proofs_match_fresh_ones() {
+        for policy in [OddNodePolicy::Duplicate, OddNodePolicy::Promote] {
+            // Crosses every power-of-two boundary up to 32 leaves and one past it
+            for size in 1..=33 {
+                let mut tree = tree_of(policy, size);
+                let mut proofs: Vec<Proof> =
+                    (0..tree.len()).map(|index| tree.proof(index).unwrap().into()).collect();
+
+                tree.append(size);
+                for (index, proof) in proofs.iter_mut().enumerate() {
+                    proof.update_after_append(&tree, index).unwrap();
+                    let fresh = Proof::from(tree.proof(index).unwrap());
+                    assert_eq!(*proof, fresh, "{:?}", (policy, size, index));
+                }
+
+                // The new leaf had no proof before the append
+                let mut proof = proofs[0].clone();
+                let index = tree.len() - 1;
+                let error = MerkleError::IndexOutOfRange { index, len: size as usize };
+                assert_eq!(proof.update_after_append(&tree, index), Err(error));
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+/*
+    Serde helpers
+
+    Hashes are written as lowercase hex strings rather than byte arrays,
+    so a serialized tree is human-readable JSON:
+        { "levels": [["ab12...", "cd34..."], ["ef56..."]] }
+
+    to_json adds the leaves and the root next to the levels, for tools that render a tree:
+        { "leaves": ["ab12...", "cd34..."], "levels": [...], "root": "ef56..." }
+    The root is null for an empty tree.
+
+    Deserialized levels must have the widths their leaf count implies, so a hand-edited
+    or truncated file is rejected instead of making later lookups panic.
+*/
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::digest::Digest;
+
+use crate::hashing::{Hash, hash_from_hex_with, hash_to_hex};
+use crate::tree::{MerkleTree, has_valid_shape};
+
+/// The shape written by `to_json`, every hash already in hex.
+#[derive(Serialize)]
+struct JsonTree {
+    leaves: Vec<String>,
+    levels: Vec<Vec<String>>,
+    root: Option<String>,
+}
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Returns the leaves, every level and the root as a JSON object of hex strings.
+    pub fn to_json(&self) -> String {
+        let json = JsonTree {
+            leaves: self.leaves().map(|leaf| hash_to_hex(leaf)).collect(),
+            levels: self
+                .iter_levels()
+                .map(|level| level.iter().map(|hash| hash_to_hex(hash)).collect())
+                .collect(),
+            root: self.root().map(|root| hash_to_hex(&root)),
+        };
+
+        serde_json::to_string(&json).expect("a tree of hex strings always serializes")
+    }
+}
+
+/// Serializes every level as a list of hex strings.
+pub(crate) fn serialize<H: AsRef<[u8]>, S: Serializer>(
+    levels: &[Vec<H>],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let hex_levels: Vec<Vec<String>> = levels
+        .iter()
+        .map(|level| level.iter().map(|hash| hash_to_hex(hash.as_ref())).collect())
+        .collect();
+
+    hex_levels.serialize(serializer)
+}
+
+/// Deserializes levels written by `serialize`, rejecting bad hex, wrong hash sizes
+/// and levels whose widths don't fit the leaf count.  The hash size N is the tree's hash width.
+pub(crate) fn deserialize<'de, const N: usize, De: Deserializer<'de>>(
+    deserializer: De,
+) -> Result<Vec<Vec<Hash<N>>>, De::Error> {
+    let hex_levels: Vec<Vec<String>> = Vec::deserialize(deserializer)?;
+
+    let levels = hex_levels
+        .iter()
+        .map(|level| {
+            level
+                .iter()
+                .map(|hex| hash_from_hex_with::<N>(hex).map_err(De::Error::custom))
+                .collect()
+        })
+        .collect::<Result<Vec<Vec<Hash<N>>>, De::Error>>()?;
+
+    if !has_valid_shape(&levels) {
+        return Err(De::Error::custom("level widths do not match the leaf count"));
+    }
+
+    Ok(levels)
+}
@@ -0,0 +1,67 @@
+/*
+    Set mode
+
+    A regular tree is a multiset: the same key can be appended any number of times.
+    MerkleSet wraps a tree and remembers every key it has seen, so appending a key
+    twice is rejected instead of silently logging the same record again.
+
+    Read-only tree methods (root, proof, len, ...) are available through Deref.
+    There is no mutable access to the inner tree, so every insert goes through the check.
+*/
+
+use core::ops::Deref;
+use std::collections::HashSet;
+
+use sha2::Sha256;
+use sha2::digest::Digest;
+
+use crate::error::MerkleError;
+use crate::hashing::Key;
+use crate::tree::MerkleTree;
+
+/// An append-only Merkle tree that rejects duplicate keys.
+pub struct MerkleSet<D: Digest = Sha256, const N: usize = 32> {
+    tree: MerkleTree<D, N>,
+    seen: HashSet<Key>,
+}
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Creates an empty tree in set mode, where each key can only be appended once.
+    pub fn new_set() -> MerkleSet<D, N> {
+        MerkleSet {
+            tree: MerkleTree::new(),
+            seen: HashSet::new(),
+        }
+    }
+}
+
+impl<D: Digest, const N: usize> MerkleSet<D, N> {
+    /// Appends `key` and returns its leaf index, or MerkleError::DuplicateKey
+    /// if it was already inserted.  The tree is unchanged on error.
+    pub fn append(&mut self, key: Key) -> Result<usize, MerkleError> {
+        if !self.seen.insert(key) {
+            return Err(MerkleError::DuplicateKey(key));
+        }
+
+        self.tree.append(key);
+        Ok(self.tree.len() - 1)
+    }
+
+    /// Returns true if `key` has been inserted.  O(1), unlike scanning the leaves.
+    pub fn contains_key(&self, key: Key) -> bool {
+        self.seen.contains(&key)
+    }
+
+    /// Unwraps the underlying tree, dropping the set of seen keys.
+    pub fn into_tree(self) -> MerkleTree<D, N> {
+        self.tree
+    }
+}
+
+impl<D: Digest, const N: usize> Deref for MerkleSet<D, N> {
+    type Target = MerkleTree<D, N>;
+
+    fn deref(&self) -> &MerkleTree<D, N> {
+        &self.tree
+    }
+}
@@ -0,0 +1,246 @@
+/*
+    Shared mode
+
+    SharedTree lets many threads read one tree while another appends to it.  It wraps
+    the tree in Arc<RwLock<_>>: cloning a SharedTree clones the handle, not the tree.
+        - root, proof, len, ... take the read lock, so readers run in parallel
+        - append takes the write lock for the O(log n) right-edge update
+
+    A thread that panics while holding the lock poisons it.  Most tree operations either
+    finish or panic before changing the levels, but a runtime node hasher panicking
+    mid-append leaves the new leaf pushed under stale upper levels.  So the next call
+    to take the lock after a panic validates the tree, rebuilds it from its leaves if
+    needed, and only then clears the poison flag, rather than turning one panic into a
+    panic on every later call.
+
+    with_tree gives read access to the whole tree API for anything not wrapped here.
+*/
+
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use sha2::Sha256;
+use sha2::digest::Digest;
+
+use crate::error::MerkleError;
+use crate::hashing::{Hash, Key};
+use crate::key::MerkleKey;
+use crate::tree::MerkleTree;
+
+/// A cloneable, thread-safe handle to one Merkle tree.
+pub struct SharedTree<D: Digest = Sha256, const N: usize = 32> {
+    tree: Arc<RwLock<MerkleTree<D, N>>>,
+}
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Creates an empty tree behind a shared handle.
+    pub fn new_shared() -> SharedTree<D, N> {
+        MerkleTree::new().into_shared()
+    }
+
+    /// Moves this tree behind a shared handle, keeping its leaves and settings.
+    pub fn into_shared(self) -> SharedTree<D, N> {
+        SharedTree {
+            tree: Arc::new(RwLock::new(self)),
+        }
+    }
+}
+
+impl<D: Digest, const N: usize> SharedTree<D, N> {
+    /// Appends `key` under the write lock and returns its leaf index.
+    pub fn append(&self, key: Key) -> usize {
+        self.append_key(key)
+    }
+
+    /// Same as `append`, for a key of any `MerkleKey` type like `MerkleTree::append_key`.
+    pub fn append_key<K: MerkleKey>(&self, key: K) -> usize {
+        self.write().append_indexed(key)
+    }
+
+    /// Returns the current root under the read lock, or None if the tree is empty.
+    pub fn root(&self) -> Option<Hash<N>> {
+        self.read().root()
+    }
+
+    /// Returns the current root, or MerkleError::Empty if the tree is empty.
+    pub fn try_root(&self) -> Result<Hash<N>, MerkleError> {
+        self.read().try_root()
+    }
+
+    /// Returns the proof for the leaf at `index` under the read lock, or None if out of range.
+    pub fn proof(&self, index: usize) -> Option<Vec<(Hash<N>, bool)>> {
+        self.read().proof(index)
+    }
+
+    /// Returns the number of leaves.
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    /// Returns true if the tree has no leaves.
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// Runs `f` on the tree under the read lock, e.g. to take a root and a proof
+    /// from the same tree size.
+    pub fn with_tree<R>(&self, f: impl FnOnce(&MerkleTree<D, N>) -> R) -> R {
+        f(&self.read())
+    }
+
+    /// Takes the read lock, repairing the tree first if the lock is poisoned.
+    fn read(&self) -> RwLockReadGuard<'_, MerkleTree<D, N>> {
+        loop {
+            if let Ok(guard) = self.tree.read() {
+                return guard;
+            }
+            // A read guard can't repair the tree, so repair it under the write lock
+            drop(self.write());
+        }
+    }
+
+    /// Takes the write lock, repairing the tree first if the lock is poisoned.
+    fn write(&self) -> RwLockWriteGuard<'_, MerkleTree<D, N>> {
+        self.tree.write().unwrap_or_else(|poisoned| {
+            let mut tree = poisoned.into_inner();
+            // A panic mid-append may have left upper levels that don't match the leaves
+            if tree.validate().is_err() {
+                tree.rebuild();
+            }
+            self.tree.clear_poison();
+            tree
+        })
+    }
+}
+
+// Cloning shares the tree.
+impl<D: Digest, const N: usize> Clone for SharedTree<D, N> {
+    fn clone(&self) -> Self {
+        SharedTree {
+            tree: Arc::clone(&self.tree),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hashing::{hash_internal, hash_key, hash_leaf_bytes};
+    use crate::proof::verify_proof;
+    use crate::tree::Sha256Tree;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    const KEYS: u64 = 300;
+
+    #[test]
+    fn readers_see_consistent_trees_while_appending() {
+        let shared = Sha256Tree::new_shared();
+
+        let writer = {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                for key in 0..KEYS {
+                    assert_eq!(shared.append(key), key as usize);
+                }
+            })
+        };
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    let mut last_len = 0;
+                    while last_len < KEYS as usize {
+                        // A root and proof taken under one lock always belong together
+                        let (len, checked) = shared.with_tree(|tree| {
+                            let Some(root) = tree.root() else {
+                                return (0, true);
+                            };
+                            let index = tree.len() / 2;
+                            let proof = tree.proof(index).unwrap();
+                            (tree.len(), verify_proof(hash_key(index as u64), &proof, root))
+                        });
+
+                        assert!(checked, "proof failed at {} leaves", len);
+                        assert!(len >= last_len, "tree shrank from {} to {}", last_len, len);
+                        last_len = len;
+                    }
+                })
+            })
+            .collect();
+
+        writer.join().unwrap();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        let mut expected = Sha256Tree::new();
+        expected.append_many(&(0..KEYS).collect::<Vec<_>>());
+        assert_eq!(shared.len(), KEYS as usize);
+        assert_eq!(shared.root(), expected.root());
+    }
+
+    #[test]
+    fn poisoned_lock_is_ignored() {
+        // A leaf function that panics on one key, while append holds the write lock
+        let poison = 13u64.to_be_bytes();
+        let tree = Sha256Tree::with_hasher(
+            move |data| {
+                assert_ne!(data, poison, "refusing to hash key 13");
+                hash_leaf_bytes(data)
+            },
+            hash_internal,
+        );
+        let shared = tree.into_shared();
+        for key in 0..13u64 {
+            shared.append(key);
+        }
+
+        let panicking = shared.clone();
+        let result = thread::spawn(move || panicking.append(13u64)).join();
+        assert!(result.is_err());
+        assert!(shared.tree.is_poisoned());
+        assert!(!shared.tree.read().unwrap_or_else(|e| e.into_inner()).is_empty());
+
+        // The panic came before any change, so the tree is intact and still usable
+        let mut expected = Sha256Tree::new();
+        expected.append_many(&(0..13).collect::<Vec<_>>());
+        assert_eq!(shared.root(), expected.root());
+        assert_eq!(shared.len(), 13);
+
+        assert_eq!(shared.append(14u64), 13);
+        expected.append(14u64);
+        assert_eq!(shared.try_root(), expected.try_root());
+        assert_eq!(shared.proof(13), expected.proof(13));
+    }
+
+    #[test]
+    fn poisoned_lock_repairs_a_half_appended_tree() {
+        // A node function that panics once, after append has pushed the leaf
+        let armed = Arc::new(AtomicBool::new(false));
+        let trigger = Arc::clone(&armed);
+        let tree = Sha256Tree::with_hasher(hash_leaf_bytes, move |left, right| {
+            assert!(!trigger.swap(false, Ordering::SeqCst), "node hasher failed");
+            hash_internal(left, right)
+        });
+        let shared = tree.into_shared();
+        for key in 0..12u64 {
+            shared.append(key);
+        }
+
+        armed.store(true, Ordering::SeqCst);
+        let panicking = shared.clone();
+        assert!(thread::spawn(move || panicking.append(12u64)).join().is_err());
+        assert!(shared.tree.is_poisoned());
+
+        // The leaf went in, so the repaired tree has 13 leaves and the matching levels
+        let mut expected = Sha256Tree::new();
+        expected.append_many(&(0..13).collect::<Vec<_>>());
+        assert_eq!(shared.root(), expected.root());
+        assert!(!shared.tree.is_poisoned());
+        assert!(shared.with_tree(|tree| tree.validate()).is_ok());
+        for index in 0..13 {
+            assert_eq!(shared.proof(index), expected.proof(index));
+        }
+    }
+}
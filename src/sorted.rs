@@ -0,0 +1,276 @@
+/*
+    Sorted mode
+
+    SortedTree wraps a tree whose keys must be appended in strictly increasing order.
+    With the leaves sorted, a missing key can be proven absent by its would-be neighbors:
+        - left:  the largest present key below it, at some index i
+        - right: the smallest present key above it, at index i + 1
+    Inclusion proofs for both, bound to their indices, show that nothing sits between them.
+    A key below the first leaf needs only the right neighbor at index 0, and a key above
+    the last leaf only the left neighbor at index tree_size - 1.
+
+    The neighbors are checked with the same bare sibling lists as verify_proof_indexed,
+    so the verifier derives each side from the index and never trusts a claimed one.
+
+    Read-only tree methods (root, proof, len, ...) are available through Deref.
+*/
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+use sha2::Sha256;
+use sha2::digest::Digest;
+
+use crate::error::MerkleError;
+use crate::hashing::{Hash, Key, hash_key_with, hash_to_hex};
+use crate::proof::verify_proof_indexed_with;
+use crate::tree::{MerkleTree, OddNodePolicy};
+
+/// An append-only Merkle tree whose keys are strictly increasing.
+pub struct SortedTree<D: Digest = Sha256, const N: usize = 32> {
+    tree: MerkleTree<D, N>,
+    keys: Vec<Key>,
+}
+
+/// A present key next to a missing one, with its inclusion proof.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Neighbor<const N: usize = 32> {
+    /// The neighboring key.
+    pub key: Key,
+    /// Its leaf index.
+    pub index: usize,
+    /// Sibling hashes from its leaf up to the root, as for `verify_proof_indexed`.
+    pub siblings: Vec<Hash<N>>,
+}
+
+/// A proof that a key is not in a sorted tree: the present keys on either side of it.
+pub struct AbsenceProof<D: Digest = Sha256, const N: usize = 32> {
+    /// Number of leaves in the tree the proof was made from.
+    pub tree_size: usize,
+    /// The largest smaller key, None if the missing key is below every leaf.
+    pub left: Option<Neighbor<N>>,
+    /// The smallest larger key, None if the missing key is above every leaf.
+    pub right: Option<Neighbor<N>>,
+    digest: PhantomData<D>,
+}
+
+impl<D: Digest, const N: usize> AbsenceProof<D, N> {
+    /// Assembles an absence proof from its parts, e.g. after receiving it over the wire.
+    pub fn new(tree_size: usize, left: Option<Neighbor<N>>, right: Option<Neighbor<N>>) -> Self {
+        AbsenceProof {
+            tree_size,
+            left,
+            right,
+            digest: PhantomData,
+        }
+    }
+}
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Creates an empty tree in sorted mode, where keys must be appended in increasing order.
+    pub fn new_sorted() -> SortedTree<D, N> {
+        SortedTree {
+            tree: MerkleTree::new(),
+            keys: Vec::new(),
+        }
+    }
+}
+
+impl<D: Digest, const N: usize> SortedTree<D, N> {
+    /// Appends `key` and returns its leaf index, or MerkleError::UnsortedKey if it isn't
+    /// larger than the last key.  The tree is unchanged on error.
+    pub fn append(&mut self, key: Key) -> Result<usize, MerkleError> {
+        if let Some(&last) = self.keys.last()
+            && key <= last
+        {
+            return Err(MerkleError::UnsortedKey { key, last });
+        }
+
+        self.keys.push(key);
+        self.tree.append(key);
+        Ok(self.tree.len() - 1)
+    }
+
+    /// Returns the leaf index of `key`, or None if it isn't in the tree.  O(log n).
+    pub fn index_of_key(&self, key: Key) -> Option<usize> {
+        self.keys.binary_search(&key).ok()
+    }
+
+    /// Returns a proof that `key` is not in the tree, or None if it is or the tree is empty.
+    pub fn absence_proof(&self, key: Key) -> Option<AbsenceProof<D, N>> {
+        if self.keys.is_empty() {
+            return None;
+        }
+
+        // The position key would be inserted at is the index of its right neighbor
+        let right = self.keys.binary_search(&key).err()?;
+
+        let neighbor = |index: usize| {
+            self.keys.get(index).map(|&key| Neighbor {
+                key,
+                index,
+                siblings: self
+                    .tree
+                    .compact_proof(index)
+                    .expect("index of a stored key is in range"),
+            })
+        };
+
+        Some(AbsenceProof::new(
+            self.keys.len(),
+            right.checked_sub(1).and_then(neighbor),
+            neighbor(right),
+        ))
+    }
+
+    /// Unwraps the underlying tree, dropping the key index.
+    pub fn into_tree(self) -> MerkleTree<D, N> {
+        self.tree
+    }
+}
+
+impl<D: Digest, const N: usize> Deref for SortedTree<D, N> {
+    type Target = MerkleTree<D, N>;
+
+    fn deref(&self) -> &MerkleTree<D, N> {
+        &self.tree
+    }
+}
+
+/// Checks that `key` is absent from the sorted tree with `root`.
+/// Expects a default SHA-256 tree with duplicated odd nodes.
+pub fn verify_absence(key: Key, proof: &AbsenceProof, root: Hash) -> bool {
+    verify_absence_with::<Sha256, 32>(OddNodePolicy::Duplicate, key, proof, root)
+}
+
+/// Same as `verify_absence`, for a tree hashed with the digest `D` using `policy`.
+pub fn verify_absence_with<D: Digest, const N: usize>(
+    policy: OddNodePolicy,
+    key: Key,
+    proof: &AbsenceProof<D, N>,
+    root: Hash<N>,
+) -> bool {
+    let included = |neighbor: &Neighbor<N>| {
+        verify_proof_indexed_with::<D, N>(
+            policy,
+            hash_key_with::<D, N>(neighbor.key),
+            neighbor.index,
+            proof.tree_size,
+            &neighbor.siblings,
+            root,
+        )
+    };
+
+    // The neighbors must bracket the key at adjacent indices, or sit at either end of the tree
+    let bracketed = match (&proof.left, &proof.right) {
+        (Some(left), Some(right)) => {
+            left.key < key && key < right.key && left.index.checked_add(1) == Some(right.index)
+        }
+        (Some(left), None) => left.key < key && left.index.checked_add(1) == Some(proof.tree_size),
+        (None, Some(right)) => key < right.key && right.index == 0,
+        (None, None) => false,
+    };
+
+    bracketed
+        && proof.left.as_ref().is_none_or(included)
+        && proof.right.as_ref().is_none_or(included)
+}
+
+// Implemented by hand so that D itself doesn't need Clone or Debug.
+impl<D: Digest, const N: usize> Clone for AbsenceProof<D, N> {
+    fn clone(&self) -> Self {
+        AbsenceProof::new(self.tree_size, self.left.clone(), self.right.clone())
+    }
+}
+
+impl<const N: usize> fmt::Debug for Neighbor<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex_siblings: Vec<String> = self.siblings.iter().map(|hash| hash_to_hex(hash)).collect();
+
+        f.debug_struct("Neighbor")
+            .field("key", &self.key)
+            .field("index", &self.index)
+            .field("siblings", &hex_siblings)
+            .finish()
+    }
+}
+
+impl<D: Digest, const N: usize> fmt::Debug for AbsenceProof<D, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AbsenceProof")
+            .field("tree_size", &self.tree_size)
+            .field("left", &self.left)
+            .field("right", &self.right)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::Sha256Tree;
+
+    /// A sorted tree over 10, 20, ..., 50: five leaves, so one level is odd.
+    fn sorted() -> SortedTree {
+        let mut tree = Sha256Tree::new_sorted();
+        for key in (10..=50).step_by(10) {
+            tree.append(key).unwrap();
+        }
+        tree
+    }
+
+    #[test]
+    fn absent_keys_are_proven_absent() {
+        let tree = sorted();
+        let root = tree.root().unwrap();
+
+        // Below the first leaf, between two neighbors, and above the last leaf
+        let cases = [(5, None, Some(0)), (25, Some(1), Some(2)), (60, Some(4), None)];
+        for (key, left, right) in cases {
+            let proof = tree.absence_proof(key).unwrap();
+            assert_eq!(proof.left.as_ref().map(|n| n.index), left, "key {}", key);
+            assert_eq!(proof.right.as_ref().map(|n| n.index), right, "key {}", key);
+            assert!(verify_absence(key, &proof, root), "key {}", key);
+        }
+    }
+
+    #[test]
+    fn present_keys_have_no_absence_proof() {
+        let tree = sorted();
+        let root = tree.root().unwrap();
+        assert!(tree.absence_proof(30).is_none());
+
+        // Neither the proof for a nearby key nor a pair around a present key passes for it
+        let around = tree.absence_proof(25).unwrap();
+        assert!(!verify_absence(20, &around, root));
+        assert!(!verify_absence(30, &around, root));
+
+        let neighbor = |index: usize| Neighbor {
+            key: tree.keys[index],
+            index,
+            siblings: tree.compact_proof(index).unwrap(),
+        };
+        let skipping = AbsenceProof::new(tree.len(), Some(neighbor(1)), Some(neighbor(3)));
+        assert!(!verify_absence(30, &skipping, root));
+
+        // A neighbor whose siblings don't lead to the root is rejected
+        let mut tampered = tree.absence_proof(25).unwrap();
+        tampered.left.as_mut().unwrap().siblings[0][0] ^= 1;
+        assert!(!verify_absence(25, &tampered, root));
+    }
+
+    #[test]
+    fn out_of_order_append_is_rejected() {
+        let mut tree = sorted();
+        let root = tree.root();
+
+        assert_eq!(tree.append(50), Err(MerkleError::UnsortedKey { key: 50, last: 50 }));
+        assert_eq!(tree.append(15), Err(MerkleError::UnsortedKey { key: 15, last: 50 }));
+        assert_eq!(tree.len(), 5);
+        assert_eq!(tree.root(), root);
+        assert_eq!(tree.append(51), Ok(5));
+    }
+}
@@ -0,0 +1,295 @@
+/*
+    Sparse mode
+
+    MerkleTree places leaves in append order.  SparseMerkleTree instead gives every
+    possible u64 key its own leaf slot: a fixed tree of 64 levels above 2^64 leaves,
+    where the key's bits (least significant first) pick the path from the leaf to the root.
+
+    Almost every slot is empty, so only the non-empty nodes are stored.  An empty subtree
+    has the same hash everywhere on its level, computed once per tree:
+        - empty[0]     = all zeros (an empty leaf)
+        - empty[l + 1] = H(0x01 || empty[l] || empty[l])
+
+    A filled slot holds leaf = H(0x00 || value_hash), with the same domain separation as
+    the append-only tree, so it can never equal the all-zero empty leaf.
+
+    A proof is the 64 sibling hashes from the leaf up to the root; the sides follow from
+    the key.  The same proof shape shows a key is absent: the slot folds up to the root
+    from the empty leaf instead of a value's leaf.
+*/
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+
+use sha2::Sha256;
+use sha2::digest::Digest;
+
+use crate::hashing::{Hash, Key, ct_eq, hash_internal_with, hash_leaf_bytes_with, hash_to_hex};
+
+/// Number of levels above the leaves, one per key bit.
+pub const SPARSE_DEPTH: usize = Key::BITS as usize;
+
+/// A fixed-depth Merkle tree with one leaf slot per u64 key.
+pub struct SparseMerkleTree<D: Digest = Sha256, const N: usize = 32> {
+    /// Value hash inserted at every filled key.
+    values: BTreeMap<Key, Hash<N>>,
+    /// Every node that differs from the empty subtree of its level, by (level, index).
+    nodes: BTreeMap<(usize, Key), Hash<N>>,
+    /// Hash of an empty subtree at each level, leaves first, root level last.
+    empty: Vec<Hash<N>>,
+    digest: PhantomData<D>,
+}
+
+impl<D: Digest, const N: usize> SparseMerkleTree<D, N> {
+    /// Creates a tree with every slot empty.
+    pub fn new() -> Self {
+        SparseMerkleTree {
+            values: BTreeMap::new(),
+            nodes: BTreeMap::new(),
+            empty: empty_hashes::<D, N>(),
+            digest: PhantomData,
+        }
+    }
+
+    /// Stores `value_hash` in the slot of `key`, replacing any previous value,
+    /// and recomputes the 64 nodes above it.
+    pub fn insert(&mut self, key: Key, value_hash: Hash<N>) {
+        self.values.insert(key, value_hash);
+        self.nodes.insert((0, key), sparse_leaf::<D, N>(&value_hash));
+
+        for level in 1..=SPARSE_DEPTH {
+            let index = node_index(key, level);
+            let left = self.node(level - 1, 2 * index);
+            let right = self.node(level - 1, 2 * index + 1);
+            self.nodes.insert((level, index), hash_internal_with::<D, N>(left, right));
+        }
+    }
+
+    /// Returns the value hash stored for `key`, or None if its slot is empty.
+    pub fn get(&self, key: Key) -> Option<Hash<N>> {
+        self.values.get(&key).copied()
+    }
+
+    /// Returns true if `key` has a value.
+    pub fn contains_key(&self, key: Key) -> bool {
+        self.values.contains_key(&key)
+    }
+
+    /// Returns the number of filled slots.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns true if every slot is empty.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the root hash.  An empty tree has the all-empty root, never None.
+    pub fn root(&self) -> Hash<N> {
+        self.node(SPARSE_DEPTH, 0)
+    }
+
+    /// Returns the 64 sibling hashes from the slot of `key` up to the root, leaf side first.
+    /// Proves the key's value with `verify_sparse_proof`, or its absence with
+    /// `verify_non_membership` if the slot is empty.
+    pub fn proof(&self, key: Key) -> Vec<Hash<N>> {
+        (0..SPARSE_DEPTH)
+            .map(|level| self.node(level, node_index(key, level) ^ 1))
+            .collect()
+    }
+
+    /// Returns a proof that `key` is absent, or None if it has a value.
+    pub fn non_membership_proof(&self, key: Key) -> Option<Vec<Hash<N>>> {
+        if self.contains_key(key) {
+            return None;
+        }
+        Some(self.proof(key))
+    }
+
+    /// The stored node at (level, index), or the empty subtree hash of that level.
+    fn node(&self, level: usize, index: Key) -> Hash<N> {
+        self.nodes.get(&(level, index)).copied().unwrap_or(self.empty[level])
+    }
+}
+
+/// Hash of an empty subtree at each level 0..=SPARSE_DEPTH.
+fn empty_hashes<D: Digest, const N: usize>() -> Vec<Hash<N>> {
+    let mut empty = Vec::with_capacity(SPARSE_DEPTH + 1);
+    empty.push([0u8; N]);
+    for level in 0..SPARSE_DEPTH {
+        empty.push(hash_internal_with::<D, N>(empty[level], empty[level]));
+    }
+    empty
+}
+
+/// The leaf stored in a filled slot: H(0x00 || value_hash).
+fn sparse_leaf<D: Digest, const N: usize>(value_hash: &Hash<N>) -> Hash<N> {
+    hash_leaf_bytes_with::<D, N>(value_hash)
+}
+
+/// Position at `level` of the node above the slot of `key`.
+fn node_index(key: Key, level: usize) -> Key {
+    key.checked_shr(level as u32).unwrap_or(0)
+}
+
+/// Folds a sparse proof into `leaf` along the path picked by `key`.
+/// None unless the proof has exactly one sibling per level.
+fn sparse_root<D: Digest, const N: usize>(
+    key: Key,
+    leaf: Hash<N>,
+    proof: &[Hash<N>],
+) -> Option<Hash<N>> {
+    if proof.len() != SPARSE_DEPTH {
+        return None;
+    }
+
+    let mut current = leaf;
+    for (level, sibling) in proof.iter().enumerate() {
+        // Bit `level` of the key says whether this node is a left (0) or right (1) child
+        current = if node_index(key, level).is_multiple_of(2) {
+            hash_internal_with::<D, N>(current, *sibling)
+        } else {
+            hash_internal_with::<D, N>(*sibling, current)
+        };
+    }
+
+    Some(current)
+}
+
+/// Checks that `key` holds `value_hash` in the SHA-256 sparse tree with `root`.
+pub fn verify_sparse_proof(key: Key, value_hash: Hash, proof: &[Hash], root: Hash) -> bool {
+    verify_sparse_proof_with::<Sha256, 32>(key, value_hash, proof, root)
+}
+
+/// Same as `verify_sparse_proof`, for a tree hashed with the digest `D` into N-byte hashes.
+pub fn verify_sparse_proof_with<D: Digest, const N: usize>(
+    key: Key,
+    value_hash: Hash<N>,
+    proof: &[Hash<N>],
+    root: Hash<N>,
+) -> bool {
+    sparse_root::<D, N>(key, sparse_leaf::<D, N>(&value_hash), proof)
+        .is_some_and(|computed| ct_eq(&computed, &root))
+}
+
+/// Checks that the slot of `key` is empty in the SHA-256 sparse tree with `root`.
+pub fn verify_non_membership(key: Key, proof: &[Hash], root: Hash) -> bool {
+    verify_non_membership_with::<Sha256, 32>(key, proof, root)
+}
+
+/// Same as `verify_non_membership`, for a tree hashed with the digest `D` into N-byte hashes.
+pub fn verify_non_membership_with<D: Digest, const N: usize>(
+    key: Key,
+    proof: &[Hash<N>],
+    root: Hash<N>,
+) -> bool {
+    sparse_root::<D, N>(key, [0u8; N], proof).is_some_and(|computed| ct_eq(&computed, &root))
+}
+
+impl<D: Digest, const N: usize> Default for SparseMerkleTree<D, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Implemented by hand so that D itself doesn't need Clone or Debug.
+impl<D: Digest, const N: usize> Clone for SparseMerkleTree<D, N> {
+    fn clone(&self) -> Self {
+        SparseMerkleTree {
+            values: self.values.clone(),
+            nodes: self.nodes.clone(),
+            empty: self.empty.clone(),
+            digest: PhantomData,
+        }
+    }
+}
+
+/// Prints the root and the filled slots, hashes as hex.
+impl<D: Digest, const N: usize> fmt::Debug for SparseMerkleTree<D, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex_values: BTreeMap<Key, String> = self
+            .values
+            .iter()
+            .map(|(key, value)| (*key, hash_to_hex(value)))
+            .collect();
+
+        f.debug_struct("SparseMerkleTree")
+            .field("root", &hash_to_hex(&self.root()))
+            .field("values", &hex_values)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashing::hash_key;
+
+    /// A tree holding the edge keys 0 and u64::MAX and one key in between.
+    fn filled() -> SparseMerkleTree {
+        let mut tree = SparseMerkleTree::new();
+        for key in [0, 1 << 40, Key::MAX] {
+            tree.insert(key, hash_key(key));
+        }
+        tree
+    }
+
+    #[test]
+    fn membership_proofs_round_trip() {
+        let tree = filled();
+        let root = tree.root();
+        for key in [0, 1 << 40, Key::MAX] {
+            let proof = tree.proof(key);
+            assert_eq!(proof.len(), SPARSE_DEPTH);
+            assert!(verify_sparse_proof(key, hash_key(key), &proof, root), "key {}", key);
+            assert!(!verify_non_membership(key, &proof, root), "key {}", key);
+            assert_eq!(tree.non_membership_proof(key), None);
+        }
+    }
+
+    #[test]
+    fn non_membership_proofs_round_trip() {
+        let tree = filled();
+        let root = tree.root();
+        for key in [1, 2, (1 << 40) + 1, Key::MAX - 1] {
+            let proof = tree.non_membership_proof(key).unwrap();
+            assert!(verify_non_membership(key, &proof, root), "key {}", key);
+            assert!(!verify_sparse_proof(key, hash_key(key), &proof, root), "key {}", key);
+        }
+
+        // Every slot of an empty tree is provably empty, key 0 and u64::MAX included
+        let empty = SparseMerkleTree::<Sha256, 32>::new();
+        for key in [0, Key::MAX] {
+            let proof = empty.non_membership_proof(key).unwrap();
+            assert!(verify_non_membership(key, &proof, empty.root()));
+        }
+    }
+
+    #[test]
+    fn tampered_proofs_are_rejected() {
+        let tree = filled();
+        let root = tree.root();
+
+        for key in [0, Key::MAX] {
+            let proof = tree.proof(key);
+            for level in [0, SPARSE_DEPTH / 2, SPARSE_DEPTH - 1] {
+                let mut tampered = proof.clone();
+                tampered[level][0] ^= 1;
+                assert!(!verify_sparse_proof(key, hash_key(key), &tampered, root));
+            }
+
+            // A wrong value, a proof of the wrong length, or another key's proof
+            assert!(!verify_sparse_proof(key, hash_key(key ^ 1), &proof, root));
+            assert!(!verify_sparse_proof(key, hash_key(key), &proof[1..], root));
+        }
+        assert!(!verify_sparse_proof(0, hash_key(0), &tree.proof(Key::MAX), root));
+
+        let mut tampered = tree.non_membership_proof(2).unwrap();
+        tampered[1][0] ^= 1;
+        assert!(!verify_non_membership(2, &tampered, root));
+    }
+}
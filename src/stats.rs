@@ -0,0 +1,55 @@
+/*
+    Tree statistics
+
+    A summary of the tree's shape for monitoring.  Everything is derived from the
+    length of each level, so computing it is O(height), never a walk over the leaves.
+*/
+
+use sha2::digest::Digest;
+
+use crate::tree::{MerkleTree, OddNodePolicy};
+
+/// Shape of a tree at one point in time, as returned by `MerkleTree::stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeStats {
+    /// Number of leaves.
+    pub leaf_count: usize,
+    /// Number of levels, leaves and root included (0 if empty).
+    pub height: usize,
+    /// Number of stored nodes over all levels.
+    pub total_nodes: usize,
+    /// True if the leaf count is a power of two, so no level has a lone node.
+    pub is_perfect: bool,
+    /// Number of lone nodes hashed with a copy of themselves (always 0 when promoting).
+    pub duplicated_nodes: usize,
+}
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Summarizes the tree's shape from its level sizes.
+    pub fn stats(&self) -> TreeStats {
+        let mut total_nodes = 0;
+        let mut lone_nodes = 0;
+
+        for level in self.iter_levels() {
+            total_nodes += level.len();
+
+            // Every level but the root with an odd length has a lone last node
+            if level.len() > 1 && !level.len().is_multiple_of(2) {
+                lone_nodes += 1;
+            }
+        }
+
+        let duplicated_nodes = match self.policy {
+            OddNodePolicy::Duplicate => lone_nodes,
+            OddNodePolicy::Promote => 0,
+        };
+
+        TreeStats {
+            leaf_count: self.len(),
+            height: self.height(),
+            total_nodes,
+            is_perfect: self.len().is_power_of_two(),
+            duplicated_nodes,
+        }
+    }
+}
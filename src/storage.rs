@@ -0,0 +1,545 @@
+/*
+    Saving and loading trees
+
+    File layout (all integers big-endian):
+        - magic:      b"MRKL"
+        - version:    u8, 1 = leaves only, 2 = every level
+        - flags:      u8, bit 0 set = Promote (else Duplicate), bit 1 set = little-endian keys,
+                      bit 2 set = a with_domain label follows the hash size
+        - hash size:  u32, bytes per hash (32 for SHA-256)
+        - bit 2 only: u32 label length, then the label bytes
+        - leaf count: u64
+        - leaves:     leaf count * hash size raw bytes
+        - version 2 only: every level above the leaves, bottom-up, each exactly
+          ceil(leaf count / 2^level) raw hashes up to the single root
+
+    save only stores the leaves.  The upper levels are rebuilt on load,
+    which keeps files at roughly half the size of the in-memory tree.
+    save_full stores every level so load_full skips the rebuild, for huge trees where
+    reloading would otherwise rehash everything.  Each loader rejects the other's version.
+    The with_domain label is stored in the header, so a loaded tree keeps hashing new leaves
+    under it.  Files without one read exactly as before the flag existed.
+    A runtime hasher can't be stored at all, so save and save_full refuse trees that have
+    one rather than write a file that loads with a different root.
+
+    build_from_reader reads a plain stream of 8-byte big-endian keys instead, with no header.
+
+    write_proof / read_proof stream single proofs in the Proof::to_bytes layout.  Its step
+    count prefix delimits each proof, so many can be written back-to-back on one socket.
+*/
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use sha2::digest::Digest;
+
+use crate::error::ProofError;
+use crate::hashing::{Endianness, Hash, Key};
+use crate::proof::Proof;
+use crate::tree::{MerkleTree, OddNodePolicy, height_for, level_width};
+
+/// Identifies a file written by this crate.
+const MAGIC: &[u8; 4] = b"MRKL";
+
+/// Format version for files holding only the leaves.
+const VERSION_LEAVES: u8 = 1;
+
+/// Format version for files holding every level.
+const VERSION_FULL: u8 = 2;
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Writes the tree's leaves to `path` in the compact binary format.
+    /// A tree with a runtime hasher is refused with InvalidInput, see `with_hasher`.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        self.check_saveable()?;
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        write_header::<N>(&mut writer, VERSION_LEAVES, self.policy, self.endianness, &self.domain)?;
+        writer.write_all(&(self.len() as u64).to_be_bytes())?;
+        for leaf in self.leaves() {
+            writer.write_all(leaf)?;
+        }
+
+        writer.flush()
+    }
+
+    /// Reads a tree written by `save`, rebuilding the upper levels from the leaves.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let (policy, endianness, domain) = read_header::<N>(&mut reader, VERSION_LEAVES)?;
+        // Counts are always u64 on disk, a 32-bit reader rejects ones it can't index
+        let leaf_count = usize::try_from(read_u64(&mut reader)?)
+            .map_err(|_| invalid_data("leaf count does not fit in this platform's usize"))?;
+
+        // Don't trust the count for preallocation, a corrupt file could claim anything
+        let mut leaves = Vec::new();
+        for _ in 0..leaf_count {
+            leaves.push(read_hash::<N>(&mut reader)?);
+        }
+        expect_end(&mut reader)?;
+
+        let mut tree = MerkleTree::with_policy(policy);
+        tree.endianness = endianness;
+        tree.domain = domain;
+        if !leaves.is_empty() {
+            tree.levels.push(leaves);
+            tree.rebuild();
+        }
+
+        Ok(tree)
+    }
+
+    /// Writes every level of the tree to `path`, so `load_full` can skip the rebuild.
+    /// A tree with a runtime hasher is refused with InvalidInput, see `with_hasher`.
+    pub fn save_full(&self, path: &Path) -> io::Result<()> {
+        self.check_saveable()?;
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        write_header::<N>(&mut writer, VERSION_FULL, self.policy, self.endianness, &self.domain)?;
+        writer.write_all(&(self.len() as u64).to_be_bytes())?;
+        for level in self.iter_levels() {
+            for hash in level {
+                writer.write_all(hash)?;
+            }
+        }
+
+        writer.flush()
+    }
+
+    /// Reads a tree written by `save_full`, taking the stored levels as they are.
+    /// Files written by `save` are rejected, use `load` for those.
+    pub fn load_full(path: &Path) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let (policy, endianness, domain) = read_header::<N>(&mut reader, VERSION_FULL)?;
+        let leaf_count = usize::try_from(read_u64(&mut reader)?)
+            .map_err(|_| invalid_data("leaf count does not fit in this platform's usize"))?;
+
+        // The leaf count fixes every level's width, a short file fails on the missing hashes
+        let mut levels = Vec::new();
+        for level in 0..height_for(leaf_count) {
+            let mut nodes = Vec::new();
+            for _ in 0..level_width(leaf_count, level) {
+                nodes.push(read_hash::<N>(&mut reader)?);
+            }
+            levels.push(nodes);
+        }
+        expect_end(&mut reader)?;
+
+        let mut tree = MerkleTree::with_policy(policy);
+        tree.endianness = endianness;
+        tree.domain = domain;
+        tree.levels = levels;
+
+        Ok(tree)
+    }
+
+    /// Fails for a tree whose hashes a loader couldn't reproduce: the closures of a
+    /// runtime hasher aren't stored, and load would rehash with D.
+    fn check_saveable(&self) -> io::Result<()> {
+        if self.hasher.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a tree with a runtime hasher can't be saved",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Builds a tree from a stream of 8-byte big-endian keys, read until EOF.
+    /// A partial key at the end of the stream is an UnexpectedEof error.
+    pub fn build_from_reader<R: Read>(reader: R) -> io::Result<Self> {
+        let mut reader = BufReader::new(reader);
+
+        let mut tree = MerkleTree::new();
+
+        // Hash keys as they arrive, the keys themselves are never collected
+        let mut leaves = Vec::new();
+        while let Some(key) = read_key(&mut reader)? {
+            leaves.push(tree.key_leaf(&key));
+        }
+
+        tree.extend_leaves(leaves);
+        Ok(tree)
+    }
+}
+
+/// Writes one proof as `Proof::to_bytes` does, ready to be followed by another.
+pub fn write_proof<W: Write, D: Digest, const N: usize>(
+    writer: &mut W,
+    proof: &Proof<D, N>,
+) -> io::Result<()> {
+    writer.write_all(&proof.to_bytes())
+}
+
+/// Reads one proof written by `write_proof`, leaving the reader at the next one.
+/// A stream that ends mid-proof is an UnexpectedEof error, a bad direction byte InvalidData.
+pub fn read_proof<R: Read, D: Digest, const N: usize>(reader: &mut R) -> io::Result<Proof<D, N>> {
+    let mut count = [0u8; 4];
+    reader.read_exact(&mut count)?;
+    let count = u32::from_be_bytes(count) as usize;
+
+    // Don't trust the count for preallocation, read step by step
+    let mut steps = Vec::new();
+    for step in 0..count {
+        let sibling = read_hash::<N>(reader)?;
+        let mut direction = [0u8; 1];
+        reader.read_exact(&mut direction)?;
+        let is_right = match direction[0] {
+            0 => false,
+            1 => true,
+            found => {
+                let err = ProofError::InvalidDirection { step, found };
+                return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+            }
+        };
+        steps.push((sibling, is_right));
+    }
+
+    Ok(Proof::new(steps))
+}
+
+/// Flag bit set when lone nodes are promoted.
+const FLAG_PROMOTE: u8 = 0b01;
+
+/// Flag bit set when keys are hashed little-endian.
+const FLAG_LITTLE_ENDIAN: u8 = 0b10;
+
+/// Flag bit set when a domain label follows the hash size.
+const FLAG_DOMAIN: u8 = 0b100;
+
+/// Writes the magic, version, settings flags, hash size and domain label if any.
+pub(crate) fn write_header<const N: usize>(
+    writer: &mut impl Write,
+    version: u8,
+    policy: OddNodePolicy,
+    endianness: Endianness,
+    domain: &[u8],
+) -> io::Result<()> {
+    let mut flags = 0;
+    if policy == OddNodePolicy::Promote {
+        flags |= FLAG_PROMOTE;
+    }
+    if endianness == Endianness::Little {
+        flags |= FLAG_LITTLE_ENDIAN;
+    }
+    if !domain.is_empty() {
+        flags |= FLAG_DOMAIN;
+    }
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[version, flags])?;
+    writer.write_all(&(N as u32).to_be_bytes())?;
+
+    if !domain.is_empty() {
+        let len = u32::try_from(domain.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "domain label too long"))?;
+        writer.write_all(&len.to_be_bytes())?;
+        writer.write_all(domain)?;
+    }
+
+    Ok(())
+}
+
+/// Reads and checks the header written by `write_header`, returning the stored settings.
+pub(crate) fn read_header<const N: usize>(
+    reader: &mut impl Read,
+    version: u8,
+) -> io::Result<(OddNodePolicy, Endianness, Vec<u8>)> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("not a merkle tree file"));
+    }
+
+    let mut bytes = [0u8; 2];
+    reader.read_exact(&mut bytes)?;
+    if bytes[0] != version {
+        return Err(invalid_data("unsupported merkle tree file version"));
+    }
+    let flags = bytes[1];
+    if flags & !(FLAG_PROMOTE | FLAG_LITTLE_ENDIAN | FLAG_DOMAIN) != 0 {
+        return Err(invalid_data("unknown tree settings flags"));
+    }
+    let policy = if flags & FLAG_PROMOTE != 0 {
+        OddNodePolicy::Promote
+    } else {
+        OddNodePolicy::Duplicate
+    };
+    let endianness = if flags & FLAG_LITTLE_ENDIAN != 0 {
+        Endianness::Little
+    } else {
+        Endianness::Big
+    };
+
+    let mut size = [0u8; 4];
+    reader.read_exact(&mut size)?;
+    if u32::from_be_bytes(size) as usize != N {
+        return Err(invalid_data("hash size does not match the tree's hash width"));
+    }
+
+    let mut domain = Vec::new();
+    if flags & FLAG_DOMAIN != 0 {
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len)?;
+        let len = u32::from_be_bytes(len) as u64;
+
+        // Don't trust the length for preallocation, a short file stops at its end
+        reader.take(len).read_to_end(&mut domain)?;
+        if domain.len() as u64 != len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated domain label"));
+        }
+        if domain.is_empty() {
+            return Err(invalid_data("domain flag set with an empty label"));
+        }
+    }
+
+    Ok((policy, endianness, domain))
+}
+
+/// Reads a big-endian u64.
+pub(crate) fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+/// Reads one big-endian key, or None at a clean end of stream.
+fn read_key(reader: &mut impl Read) -> io::Result<Option<Key>> {
+    let mut bytes = [0u8; 8];
+    let mut filled = 0;
+
+    while filled < bytes.len() {
+        match reader.read(&mut bytes[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+
+    match filled {
+        0 => Ok(None),
+        8 => Ok(Some(Key::from_be_bytes(bytes))),
+        _ => Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("truncated key: {} trailing bytes", filled),
+        )),
+    }
+}
+
+/// Reads one raw N-byte hash.
+pub(crate) fn read_hash<const N: usize>(reader: &mut impl Read) -> io::Result<Hash<N>> {
+    let mut hash = [0u8; N];
+    reader.read_exact(&mut hash)?;
+    Ok(hash)
+}
+
+/// Fails if anything is left after the expected data.
+pub(crate) fn expect_end(reader: &mut impl Read) -> io::Result<()> {
+    let mut byte = [0u8; 1];
+    if reader.read(&mut byte)? != 0 {
+        return Err(invalid_data("unexpected trailing data"));
+    }
+    Ok(())
+}
+
+pub(crate) fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::Sha256Tree;
+    use std::path::PathBuf;
+
+    /// A file in the temp directory, removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let file = format!("merkle-tree-{}-{}.bin", name, std::process::id());
+            TempFile(std::env::temp_dir().join(file))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn labeled_tree() -> Sha256Tree {
+        let mut tree = Sha256Tree::with_domain(b"audit-log");
+        for key in 0..7u64 {
+            tree.append(key);
+        }
+        tree
+    }
+
+    #[test]
+    fn load_keeps_domain() {
+        let file = TempFile::new("load-domain");
+        let mut tree = labeled_tree();
+        tree.save(&file.0).unwrap();
+
+        let mut loaded = Sha256Tree::load(&file.0).unwrap();
+        assert_eq!(loaded.domain(), b"audit-log");
+        assert_eq!(loaded.root(), tree.root());
+
+        tree.append(7u64);
+        loaded.append(7u64);
+        assert_eq!(loaded.root(), tree.root());
+    }
+
+    #[test]
+    fn load_full_keeps_domain() {
+        let file = TempFile::new("load-full-domain");
+        let mut tree = labeled_tree();
+        tree.save_full(&file.0).unwrap();
+
+        let mut loaded = Sha256Tree::load_full(&file.0).unwrap();
+        assert_eq!(loaded, tree);
+
+        tree.append(7u64);
+        loaded.append(7u64);
+        assert_eq!(loaded.root(), tree.root());
+    }
+
+    #[test]
+    fn unlabeled_file_has_no_domain() {
+        let file = TempFile::new("no-domain");
+        let mut tree = Sha256Tree::rfc6962();
+        tree.append(1u64);
+        tree.save(&file.0).unwrap();
+
+        // magic, version, flags, hash size, leaf count, one leaf
+        let bytes = std::fs::read(&file.0).unwrap();
+        assert_eq!(bytes.len(), 4 + 1 + 1 + 4 + 8 + 32);
+        assert_eq!(bytes[5], FLAG_PROMOTE);
+
+        let loaded = Sha256Tree::load(&file.0).unwrap();
+        assert!(loaded.domain().is_empty());
+        assert_eq!(loaded, tree);
+    }
+
+    #[test]
+    fn runtime_hasher_is_not_saved() {
+        let file = TempFile::new("runtime-hasher");
+        let mut tree = Sha256Tree::with_hasher(
+            crate::hashing::hash_leaf_bytes_with::<sha2::Sha512, 32>,
+            crate::hashing::hash_internal_with::<sha2::Sha512, 32>,
+        );
+        tree.append(1u64);
+
+        assert_eq!(tree.save(&file.0).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(tree.save_full(&file.0).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(!file.0.exists());
+    }
+
+    #[test]
+    fn truncated_domain_is_rejected() {
+        let file = TempFile::new("truncated-domain");
+        labeled_tree().save(&file.0).unwrap();
+
+        let bytes = std::fs::read(&file.0).unwrap();
+        std::fs::write(&file.0, &bytes[..16]).unwrap();
+
+        let err = Sha256Tree::load(&file.0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    /// Trees in every combination of settings the header records.
+    fn sample_trees() -> Vec<Sha256Tree> {
+        let mut trees = Vec::new();
+        for size in [0, 1, 2, 5, 16, 33] {
+            for policy in [OddNodePolicy::Duplicate, OddNodePolicy::Promote] {
+                let mut tree = Sha256Tree::with_policy(policy);
+                tree.endianness = if size % 2 == 0 { Endianness::Big } else { Endianness::Little };
+                if size > 10 {
+                    tree.domain = b"audit-log".to_vec();
+                }
+                tree.append_many(&(0..size).collect::<Vec<_>>());
+                trees.push(tree);
+            }
+        }
+        trees
+    }
+
+    #[test]
+    fn both_formats_round_trip() {
+        let file = TempFile::new("round-trip");
+        for tree in sample_trees() {
+            let context = (tree.len(), tree.policy(), tree.endianness());
+
+            tree.save(&file.0).unwrap();
+            let loaded = Sha256Tree::load(&file.0).unwrap();
+            assert_eq!(loaded, tree, "{:?}", context);
+            assert_eq!(loaded.endianness(), tree.endianness(), "{:?}", context);
+            assert_eq!(loaded.domain(), tree.domain(), "{:?}", context);
+            assert!(Sha256Tree::load_full(&file.0).is_err(), "{:?}", context);
+
+            tree.save_full(&file.0).unwrap();
+            let loaded = Sha256Tree::load_full(&file.0).unwrap();
+            assert_eq!(loaded, tree, "{:?}", context);
+            assert_eq!(loaded.policy(), tree.policy(), "{:?}", context);
+            assert_eq!(loaded.domain(), tree.domain(), "{:?}", context);
+            assert!(loaded.validate().is_ok(), "{:?}", context);
+            assert!(Sha256Tree::load(&file.0).is_err(), "{:?}", context);
+        }
+    }
+
+    #[test]
+    fn corrupt_and_short_files_are_errors() {
+        let file = TempFile::new("corrupt");
+        let mut tree = labeled_tree();
+        tree.policy = OddNodePolicy::Promote;
+
+        type Loader = fn(&Path) -> io::Result<Sha256Tree>;
+        type Saver = fn(&Sha256Tree, &Path) -> io::Result<()>;
+        let formats: [(Loader, Saver); 2] = [
+            (Sha256Tree::load, |tree, path| tree.save(path)),
+            (Sha256Tree::load_full, |tree, path| tree.save_full(path)),
+        ];
+
+        for (load, save) in formats {
+            save(&tree, &file.0).unwrap();
+            let bytes = std::fs::read(&file.0).unwrap();
+            let load_bytes = |bytes: &[u8]| {
+                std::fs::write(&file.0, bytes).unwrap();
+                load(&file.0)
+            };
+
+            // Every strict prefix is short somewhere, and one extra byte is too many
+            for len in 0..bytes.len() {
+                assert!(load_bytes(&bytes[..len]).is_err(), "prefix of {} bytes", len);
+            }
+            assert!(load_bytes(&[&bytes[..], &[0]].concat()).is_err());
+
+            // Magic, version, an unknown flag, the hash size, and an absurd leaf count
+            let count_at = 4 + 1 + 1 + 4 + 4 + b"audit-log".len();
+            let corruptions = [(0, b'X'), (4, 9), (5, 0b1000 | bytes[5]), (9, 64)];
+            for (offset, byte) in corruptions {
+                let mut corrupt = bytes.clone();
+                corrupt[offset] = byte;
+                let err = load_bytes(&corrupt).unwrap_err();
+                assert_eq!(err.kind(), io::ErrorKind::InvalidData, "byte {}", offset);
+            }
+            let mut corrupt = bytes.clone();
+            corrupt[count_at..count_at + 8].copy_from_slice(&u64::MAX.to_be_bytes());
+            assert!(load_bytes(&corrupt).is_err());
+        }
+
+        // A flipped node still loads from a full file, but validate catches it
+        tree.save_full(&file.0).unwrap();
+        let mut bytes = std::fs::read(&file.0).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        std::fs::write(&file.0, &bytes).unwrap();
+        let loaded = Sha256Tree::load_full(&file.0).unwrap();
+        assert!(loaded.validate().is_err());
+    }
+}
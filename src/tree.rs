@@ -0,0 +1,1194 @@
+use core::fmt;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::mem;
+
+// OnceLock keeps the tree Sync; without std a OnceCell still caches the root for one thread.
+#[cfg(not(feature = "std"))]
+use core::cell::OnceCell as OnceLock;
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use sha2::Sha256;
+use sha2::digest::Digest;
+
+use crate::error::MerkleError;
+use crate::hasher::Hasher;
+use crate::hashing::{Endianness, Hash, Key, ct_eq, hash_internal_with, hash_to_hex};
+use crate::key::MerkleKey;
+
+/*
+    MerkleTree structure
+*/
+
+/// How a level with an odd number of nodes builds the parent of its last node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OddNodePolicy {
+    /// Hash the lone node with a copy of itself: parent = H(node || node) (Bitcoin style).
+    #[default]
+    Duplicate,
+    /// Move the lone node up to the next level unchanged: parent = node (RFC 6962 style).
+    Promote,
+}
+
+impl OddNodePolicy {
+    /// Returns the parent of a node that has no right sibling.
+    pub(crate) fn lone_parent<D: Digest, const N: usize>(self, node: &Hash<N>) -> Hash<N> {
+        self.lone_parent_by(node, hash_internal_with::<D, N>)
+    }
+
+    /// Same as `lone_parent`, hashing a duplicated node with `hash_node`.
+    pub(crate) fn lone_parent_by<const N: usize>(
+        self,
+        node: &Hash<N>,
+        hash_node: impl FnOnce(Hash<N>, Hash<N>) -> Hash<N>,
+    ) -> Hash<N> {
+        match self {
+            OddNodePolicy::Duplicate => hash_node(*node, *node),
+            OddNodePolicy::Promote => *node,
+        }
+    }
+}
+
+/// An append-only Merkle tree storing levels of hashes.
+/// - levels[0] = leaf level
+/// - levels[last] = root level (single hash) when non-empty
+/// - an empty tree has no levels, or only an empty leaf level kept for its capacity
+///
+/// Generic over the digest `D` used for leaves and internal nodes, and the
+/// hash width N: each digest output is cut down to its first N bytes.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = ""))]
+pub struct MerkleTree<D: Digest = Sha256, const N: usize = 32> {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub(crate) levels: Vec<Vec<Hash<N>>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) policy: OddNodePolicy,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) endianness: Endianness,
+    /// Label mixed into every leaf hash, empty for none, see `with_domain`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) domain: Vec<u8>,
+    /// Runtime leaf and node hash functions replacing D, see `with_hasher`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) hasher: Option<Hasher<N>>,
+    /// Leaf hash of every key appended so far by its leaf bytes, only for trees made with `new_cached`.
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    key_cache: Option<HashMap<Vec<u8>, Hash<N>>>,
+    /// Number of times the upper levels were recomputed since creation or `clear`.
+    #[cfg(feature = "metrics")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    rebuilds: u64,
+    /// Root computed on the first root() call after a mutation, cleared by every rebuild.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    root_cache: OnceLock<Option<Hash<N>>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    digest: PhantomData<D>,
+}
+
+/// A Merkle tree hashed with SHA-256, the original configuration.
+pub type Sha256Tree = MerkleTree<Sha256, 32>;
+
+impl<D: Digest, const N: usize> MerkleTree<D, N> {
+    /// Creates an empty Merkle tree.
+    pub fn new() -> Self {
+        Self::with_policy(OddNodePolicy::default())
+    }
+
+    /// Creates an empty Merkle tree using `policy` for odd-length levels.
+    pub fn with_policy(policy: OddNodePolicy) -> Self {
+        MerkleTree { 
+            levels: Vec::new(),
+            policy,
+            endianness: Endianness::default(),
+            domain: Vec::new(),
+            hasher: None,
+            #[cfg(feature = "std")]
+            key_cache: None,
+            #[cfg(feature = "metrics")]
+            rebuilds: 0,
+            root_cache: OnceLock::new(),
+            digest: PhantomData,
+        }
+    }
+
+    /// Creates an empty Merkle tree with room for `n` leaves, so appending up to `n` keys
+    /// never reallocates the leaf level.  The upper levels still grow as they are built.
+    pub fn with_capacity(n: usize) -> Self {
+        let mut tree = Self::new();
+        tree.levels.reserve_exact(height_for(n).max(1));
+        tree.levels.push(Vec::with_capacity(n));
+        tree
+    }
+
+    /// Creates an empty Merkle tree that caches the leaf hash of every key it appends,
+    /// so keys that recur are hashed only once.  The cache grows by one entry per distinct
+    /// key and is never evicted, which is why `new()` trees don't keep one.
+    #[cfg(feature = "std")]
+    pub fn new_cached() -> Self {
+        let mut tree = Self::new();
+        tree.key_cache = Some(HashMap::new());
+        tree
+    }
+
+    /// Returns the number of distinct keys in the leaf-hash cache, 0 without `new_cached`.
+    #[cfg(feature = "std")]
+    pub fn cached_keys(&self) -> usize {
+        self.key_cache.as_ref().map_or(0, |cache| cache.len())
+    }
+
+    /// Creates an empty tree compatible with RFC 6962 (Certificate Transparency).
+    /// RFC 6962 hashes leaves as H(0x00 || data) and nodes as H(0x01 || left || right),
+    /// which are already this crate's domain-separation bytes, and promotes lone nodes
+    /// instead of duplicating them.  Use `append_bytes` to log raw entries.
+    /// The roots of the CT test data, 1 to 8 leaves, are pinned in this module's tests.
+    pub fn rfc6962() -> Self {
+        Self::with_policy(OddNodePolicy::Promote)
+    }
+
+    /// Creates an empty Merkle tree that serializes keys in `endianness` byte order before hashing.
+    /// Big-endian is the default, little-endian matches systems hashing `u64::to_le_bytes`.
+    pub fn with_endianness(endianness: Endianness) -> Self {
+        let mut tree = Self::new();
+        tree.endianness = endianness;
+        tree
+    }
+
+    /// Creates an empty Merkle tree whose leaves are hashed under the domain `label`:
+    /// H(label length || label || 0x00 || data), see `hash_key_in_domain`.
+    /// Trees with different labels never share a root, even on identical keys.
+    /// An empty label hashes exactly like `new()`.
+    pub fn with_domain(label: &[u8]) -> Self {
+        let mut tree = Self::new();
+        tree.domain = label.to_vec();
+        tree
+    }
+
+    /// Returns how this tree handles odd-length levels.
+    pub fn policy(&self) -> OddNodePolicy {
+        self.policy
+    }
+
+    /// Returns the byte order keys are serialized in before hashing.
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    /// Returns the domain label mixed into leaf hashes, empty for none.
+    pub fn domain(&self) -> &[u8] {
+        &self.domain
+    }
+
+    /// Hashes `key` into a leaf using this tree's digest and key byte order.
+    pub(crate) fn key_leaf<K: MerkleKey + ?Sized>(&self, key: &K) -> Hash<N> {
+        self.encoded_leaf(&key.to_leaf_bytes_in(self.endianness))
+    }
+
+    /// Same as `key_leaf`, remembering the hash if the tree keeps a key cache.
+    pub(crate) fn key_leaf_cached<K: MerkleKey + ?Sized>(&mut self, key: &K) -> Hash<N> {
+        let bytes = key.to_leaf_bytes_in(self.endianness);
+        let leaf = self.encoded_leaf(&bytes);
+        #[cfg(feature = "std")]
+        if let Some(cache) = self.key_cache.as_mut() {
+            cache.entry(bytes).or_insert(leaf);
+        }
+        leaf
+    }
+
+    /// Hashes a key's leaf bytes, or reads the hash from the key cache.
+    fn encoded_leaf(&self, bytes: &[u8]) -> Hash<N> {
+        #[cfg(feature = "std")]
+        if let Some(leaf) = self.key_cache.as_ref().and_then(|cache| cache.get(bytes)) {
+            return *leaf;
+        }
+        self.hash_leaf(bytes)
+    }
+
+    /// Builds a tree from `keys` with a single bottom-up pass.
+    /// Produces the same levels as appending the keys in order.
+    pub fn from_keys(keys: &[Key]) -> Self {
+        let mut tree = MerkleTree::new();
+        tree.append_many(keys);
+        tree
+    }
+
+    /// Builds a tree over already-computed leaf hashes, taking the Vec as the leaf level
+    /// and building the upper levels once.  An empty Vec gives an empty tree.
+    pub fn from_leaves(leaves: Vec<Hash<N>>) -> Self {
+        let mut tree = MerkleTree::new();
+        if !leaves.is_empty() {
+            tree.levels.push(leaves);
+            tree.rebuild();
+        }
+        tree
+    }
+
+    /// Empties the tree back to the `new()` state, keeping the leaf level's allocation.
+    pub fn clear(&mut self) {
+        self.invalidate_root();
+        #[cfg(feature = "metrics")]
+        {
+            self.rebuilds = 0;
+        }
+        self.levels.truncate(1);
+        if let Some(leaves) = self.levels.first_mut() {
+            leaves.clear();
+        }
+    }
+
+    /// Rolls the tree back to its first `len` leaves, as if the later appends never happened.
+    /// A no-op if `len` is at least the current length.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len() {
+            return;
+        }
+        if len == 0 {
+            self.clear();
+            return;
+        }
+
+        // Nodes covering only the first len leaves are unchanged, drop everything after them
+        for (level, nodes) in self.levels.iter_mut().enumerate() {
+            nodes.truncate(level_width(len, level));
+        }
+
+        // Only the new right edge may have covered dropped leaves
+        self.rebuild_right_edge();
+    }
+
+    /// Removes the most recently appended leaf and returns its hash, or None if the tree is empty.
+    /// The inverse of `append`: popping the last leaf leaves an empty tree with no root.
+    pub fn pop(&mut self) -> Option<Hash<N>> {
+        let leaf = *self.levels.first()?.last()?;
+        self.truncate(self.len() - 1);
+        Some(leaf)
+    }
+
+    /// Reserves capacity for at least `additional` more leaves before a bulk append.
+    pub fn reserve(&mut self, additional: usize) {
+        if self.levels.is_empty() {
+            self.levels.push(Vec::new());
+        }
+        self.levels[0].reserve(additional);
+    }
+
+    /// Appends a new u64 key as a leaf and rebuilds upper levels.
+    /// The key is hashed as its 8 bytes in the tree's byte order, big-endian by default.
+    /// Use `append_key` for other key types.
+    pub fn append(&mut self, key: Key) {
+        self.append_key(key);
+    }
+
+    /// Appends a key of any `MerkleKey` type as a leaf, hashed as its leaf bytes, and
+    /// rebuilds upper levels.  Integer literals need a suffix, e.g. `append_key(5u32)`.
+    pub fn append_key<K: MerkleKey>(&mut self, key: K) {
+        let leaf = self.key_leaf_cached(&key);
+        self.append_hash(leaf);
+    }
+
+    /// Appends a new key like `append` and returns the index of its leaf, ready for `proof(index)`.
+    pub fn append_indexed<K: MerkleKey>(&mut self, key: K) -> usize {
+        self.append_key(key);
+        self.len() - 1
+    }
+
+    /// Appends `key` only if no leaf holds it yet, returning true if it was appended.
+    /// Scans every leaf like `contains`, so it's O(n); `new_set()` checks in O(1).
+    pub fn append_if_absent<K: MerkleKey>(&mut self, key: K) -> bool {
+        let leaf = self.key_leaf_cached(&key);
+        if self.leaves().any(|candidate| *candidate == leaf) {
+            return false;
+        }
+
+        self.append_hash(leaf);
+        true
+    }
+
+    /// Appends a signed key as its 8 two's-complement bytes, in the tree's byte order
+    /// (big-endian by default).  The same leaf as the u64 with the same bits.
+    pub fn append_i64(&mut self, key: i64) {
+        self.append_key(key);
+    }
+
+    /// Appends a float key as its 8 IEEE 754 bytes (`f64::to_bits`) in the tree's byte
+    /// order.  Every NaN is hashed as the canonical quiet NaN 0x7ff8000000000000 so its
+    /// payload can't change the leaf; 0.0 and -0.0 are different leaves.
+    pub fn append_f64(&mut self, key: f64) {
+        self.append_key(key);
+    }
+
+    /// Appends arbitrary bytes as a leaf and rebuilds upper levels.
+    pub fn append_bytes(&mut self, data: &[u8]) {
+        self.append_hash(self.hash_leaf(data));
+    }
+
+    /// Appends an already-computed leaf hash as-is and rebuilds upper levels.
+    /// Useful when leaves are built by a different spec than H(0x00 || data).
+    pub fn append_hash(&mut self, leaf: Hash<N>) {
+        //Check if there is a leaf level, then push the leaf
+        if self.levels.is_empty(){
+            //if empty, create the leaf level with this single leaf
+            self.levels.push(vec![leaf]);
+        }
+        else{
+            //if leaf level exists, push to leaf level
+            self.levels[0].push(leaf);
+        }
+
+        self.rebuild_right_edge();
+    }
+
+    /// Appends many keys as leaves, rebuilding upper levels only once.
+    /// The resulting tree is identical to appending the keys one at a time.
+    pub fn append_many(&mut self, keys: &[Key]) {
+        self.extend(keys.iter().copied());
+    }
+
+    /// Appends already-computed leaf hashes as-is, rebuilding upper levels only once.
+    /// The pre-hashed counterpart of `append_many`, like `append_hash` is to `append`.
+    pub fn append_hashes<I: IntoIterator<Item = Hash<N>>>(&mut self, hashes: I) {
+        self.extend_leaves(hashes);
+    }
+
+    /// Appends all of `other`'s leaves, in order, rebuilding upper levels only once.
+    /// The result equals appending self's leaves followed by other's leaves.
+    pub fn extend_from(&mut self, other: &MerkleTree<D, N>) {
+        self.extend_leaves(other.leaves().cloned());
+    }
+
+    /// Appends copies of the last leaf until the leaf count is a power of two,
+    /// so every proof has the same length.  A no-op for an empty or already balanced tree.
+    pub fn pad_to_pow2(&mut self) {
+        let len = self.len();
+        if len == 0 || len.is_power_of_two() {
+            return;
+        }
+
+        let last = self.levels[0][len - 1];
+        self.extend_leaves(core::iter::repeat_n(last, len.next_power_of_two() - len));
+    }
+
+    /// Pushes leaf hashes onto levels[0], then rebuilds the upper levels a single time.
+    pub(crate) fn extend_leaves(&mut self, leaves: impl IntoIterator<Item = Hash<N>>) {
+        // Create the leaf level if needed
+        if self.levels.is_empty() {
+            self.levels.push(Vec::new());
+        }
+
+        // Push every leaf hash first
+        let before = self.levels[0].len();
+        self.levels[0].extend(leaves);
+
+        // Nothing was added, leave the tree as is
+        if self.levels[0].len() == before {
+            return;
+        }
+
+        // Then rebuild the upper levels a single time
+        self.rebuild();
+    }
+
+    /// Recomputes every level above the leaves, writing into the existing level buffers
+    /// so repeated rebuilds don't allocate once the levels have grown.
+    /// Expects levels[0] to exist and be non-empty.
+    pub(crate) fn rebuild(&mut self) {
+        self.invalidate_root();
+        self.count_rebuild();
+        //start recomputing the parent hashes, starting at level 1
+        let mut level_index = 1;
+
+        loop{
+            // Get the level below (the one we just updated or created)
+            let below = &self.levels[level_index - 1];
+
+            // If the level below has only one node, it's already the root.
+            // No need to build further levels.
+            if below.len() == 1 {
+                // Truncate any old levels above this (in case they existed).
+                self.levels.truncate(level_index);
+                break;
+            }
+
+            // Build the next level from `below` by hashing pairs, reusing the old level's buffer
+            let width = below.len().div_ceil(2);
+            let mut next_level = self.levels.get_mut(level_index).map(mem::take).unwrap_or_default();
+            next_level.clear();
+            next_level.reserve(width);
+            let below = &self.levels[level_index - 1];
+
+            let mut i = 0;
+            while i < below.len() {
+                let left = &below[i];
+
+                // If there is a right sibling, use it; otherwise apply the odd-node policy.
+                let parent = if i + 1 < below.len() {
+                    self.hash_node(*left, below[i + 1])
+                } else {
+                    self.lone_parent(left)
+                };
+
+                next_level.push(parent);
+
+                i += 2;
+            }
+
+            // Now put back or push this next level in self.levels
+            if self.levels.len() > level_index {
+                // Return the reused buffer to its slot
+                self.levels[level_index] = next_level;
+            } else {
+                // Push as a new level
+                self.levels.push(next_level);
+            }
+
+            // Move up one level
+            level_index += 1;
+        }
+
+        // Fuzzing runs in debug builds, catch a bad shape here rather than in a later lookup
+        debug_assert!(has_valid_shape(&self.levels));
+    }
+
+    /// Recomputes only the right edge above the last leaf, after a single append.
+    /// Only the last node of each level covers the new leaf, so this is O(log n).
+    /// Expects the levels above the leaves to be valid for the tree without that leaf.
+    pub(crate) fn rebuild_right_edge(&mut self) {
+        self.rebuild_path(self.levels[0].len() - 1);
+    }
+
+    /// Recomputes the ancestors of the leaf at `index`, O(log n).
+    /// Expects every other node above the leaves to be valid already.
+    pub(crate) fn rebuild_path(&mut self, index: usize) {
+        self.invalidate_root();
+        self.count_rebuild();
+
+        //position of the changed node on the current level, starting at the leaf
+        let mut index = index;
+        let mut level_index = 1;
+
+        loop{
+            let below = &self.levels[level_index - 1];
+
+            // A single node below is the root, drop anything above it
+            if below.len() == 1 {
+                self.levels.truncate(level_index);
+                break;
+            }
+
+            // Recompute the parent of the changed node from its pair
+            let parent_index = index / 2;
+            let left = &below[2 * parent_index];
+            let parent = if 2 * parent_index + 1 < below.len() {
+                self.hash_node(*left, below[2 * parent_index + 1])
+            } else {
+                self.lone_parent(left)
+            };
+
+            // Overwrite the parent, or grow the level / tree by one node after an append
+            if let Some(level) = self.levels.get_mut(level_index) {
+                if parent_index < level.len() {
+                    level[parent_index] = parent;
+                } else {
+                    level.push(parent);
+                }
+            } else {
+                self.levels.push(vec![parent]);
+            }
+
+            // Move up one level
+            index = parent_index;
+            level_index += 1;
+        }
+
+        debug_assert!(has_valid_shape(&self.levels));
+    }
+
+    /// Counts one recomputation of the upper levels, a no-op without the metrics feature.
+    fn count_rebuild(&mut self) {
+        #[cfg(feature = "metrics")]
+        {
+            self.rebuilds += 1;
+        }
+    }
+
+    /// Returns how many times the upper levels were recomputed (a full rebuild or one
+    /// append's right edge) since the tree was created or last cleared.
+    #[cfg(feature = "metrics")]
+    pub fn rebuild_count(&self) -> u64 {
+        self.rebuilds
+    }
+
+    /// Drops the cached root, every mutation must call this.
+    pub(crate) fn invalidate_root(&mut self) {
+        self.root_cache.take();
+    }
+
+    /// Returns the current root hash, or None if the tree is empty.
+    /// Cached until the next mutation, so repeated calls are O(1).
+    pub fn root(&self) -> Option<Hash<N>> {
+        *self.root_cache.get_or_init(|| self.compute_root())
+    }
+
+    /// Reads the root from the top level.
+    fn compute_root(&self) -> Option<Hash<N>> {
+        // If there are no levels, the tree is empty → no root
+        let last_level = self.levels.last()?;
+
+        // If last level is empty (shouldn't happen, but safe to check)
+        if last_level.is_empty() {
+            return None;
+        }
+
+        // Return the single hash in the top level
+        Some(last_level[0])
+    }
+
+    /// Borrows the current root hash from the top level instead of copying it,
+    /// or returns None if the tree is empty.
+    pub fn root_bytes(&self) -> Option<&[u8]> {
+        self.levels.last()?.first().map(|root| root.as_slice())
+    }
+
+    /// Returns the current root hash, or the all-zero hash for an empty tree,
+    /// so there is always a fixed-size commitment to store.
+    pub fn root_or_empty(&self) -> Hash<N> {
+        self.root_or([0u8; N])
+    }
+
+    /// Returns the current root hash, or `empty` for an empty tree.
+    /// For protocols whose empty commitment is something else, e.g. H("").
+    pub fn root_or(&self, empty: Hash<N>) -> Hash<N> {
+        self.root().unwrap_or(empty)
+    }
+
+    /// Returns the current root hash, or MerkleError::Empty if the tree is empty.
+    pub fn try_root(&self) -> Result<Hash<N>, MerkleError> {
+        self.root().ok_or(MerkleError::Empty)
+    }
+
+    /// Returns the current root as lowercase hex, or None if the tree is empty.
+    pub fn root_hex(&self) -> Option<String> {
+        self.root().map(|root| hash_to_hex(&root))
+    }
+
+    /// Returns true if both trees commit to the same root, or both are empty.
+    /// Cheaper than `==`, which compares every level and the policy.
+    /// The roots are compared in constant time.
+    pub fn root_eq(&self, other: &MerkleTree<D, N>) -> bool {
+        match (self.root(), other.root()) {
+            (Some(a), Some(b)) => ct_eq(&a, &b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+
+    /// Returns the leaf hash at `index`, or None if out of range.
+    pub fn leaf(&self, index: usize) -> Option<Hash<N>> {
+        self.levels.first()?.get(index).cloned()
+    }
+
+    /// Borrows the leaf hash at `index` instead of copying it, or returns None if out of range.
+    pub fn leaf_bytes(&self, index: usize) -> Option<&[u8]> {
+        self.levels.first()?.get(index).map(|leaf| leaf.as_slice())
+    }
+
+    /// Returns the index of the first leaf holding `key`, or None if it isn't in the tree.
+    /// This hashes the key and scans every leaf, so it's O(n); for large trees
+    /// keep a side index from key to position instead.
+    pub fn index_of<K: MerkleKey>(&self, key: K) -> Option<usize> {
+        let leaf = self.key_leaf(&key);
+        self.leaves().position(|candidate| *candidate == leaf)
+    }
+
+    /// Returns true if `key` has been appended.  O(n), see `index_of`.
+    pub fn contains<K: MerkleKey>(&self, key: K) -> bool {
+        self.index_of(key).is_some()
+    }
+
+    /// Returns the node hash at (`level`, `index`), with level 0 being the leaves,
+    /// or None if either coordinate is out of range.
+    pub fn node(&self, level: usize, index: usize) -> Option<Hash<N>> {
+        self.levels.get(level)?.get(index).cloned()
+    }
+
+    /// Iterates over the leaf hashes in insertion order.
+    pub fn leaves(&self) -> impl Iterator<Item = &Hash<N>> {
+        self.levels.first().into_iter().flatten()
+    }
+
+    /// Returns the last node of every level, from the last leaf up to the root: the right
+    /// edge that the next append recomputes.  Empty for an empty tree.
+    pub fn rightmost_path(&self) -> Vec<Hash<N>> {
+        self.iter_levels().filter_map(|level| level.last().copied()).collect()
+    }
+
+    /// Iterates over every level, from the leaves up to the root.
+    /// Yields nothing for an empty tree.
+    pub fn iter_levels(&self) -> impl Iterator<Item = &[Hash<N>]> {
+        let levels = if self.is_empty() { &[][..] } else { &self.levels[..] };
+        levels.iter().map(|level| level.as_slice())
+    }
+
+    /// Returns the number of leaves in the tree.
+    pub fn len(&self) -> usize {
+        self.levels.first().map_or(0, |leaves| leaves.len())
+    }
+
+    /// Returns true if the tree has no leaves.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of nodes on each level, leaves first.  Empty for an empty tree.
+    /// A proof has one step per entry but the last, minus any promoted lone nodes.
+    pub fn level_sizes(&self) -> Vec<usize> {
+        self.iter_levels().map(|level| level.len()).collect()
+    }
+
+    /// Returns true if the top level holds exactly one node, a proper root.
+    /// Every public mutation leaves the tree complete unless it is empty.
+    pub fn is_complete(&self) -> bool {
+        self.levels.last().is_some_and(|level| level.len() == 1)
+    }
+
+    /// Returns the number of levels, counting the leaf and root levels.
+    /// An empty tree has height 0, a single leaf has height 1.
+    pub fn height(&self) -> usize {
+        if self.is_empty() {
+            return 0;
+        }
+        self.levels.len()
+    }
+
+    /// Checks the stored levels against the leaves: the level count and every level's width
+    /// must follow from the leaf count, ending in a single root, and every node above the
+    /// leaves must be the hash of its children under the tree's policy.  O(n).
+    /// Meant for trees loaded from outside, e.g. by `load_full` or deserialization.
+    pub fn validate(&self) -> Result<(), MerkleError> {
+        let size = self.len();
+        let expected = height_for(size);
+        let found = self.levels.len();
+        if found != expected && !(size == 0 && found <= 1) {
+            return Err(MerkleError::InvalidHeight { expected, found });
+        }
+
+        for (level, nodes) in self.iter_levels().enumerate() {
+            let expected = level_width(size, level);
+            if nodes.len() != expected {
+                return Err(MerkleError::InvalidLevelWidth { level, expected, found: nodes.len() });
+            }
+        }
+
+        // Widths are right, so every parent's children exist
+        for level in 1..self.height() {
+            let below = &self.levels[level - 1];
+            for (index, node) in self.levels[level].iter().enumerate() {
+                let left = &below[2 * index];
+                let parent = match below.get(2 * index + 1) {
+                    Some(right) => self.hash_node(*left, *right),
+                    None => self.lone_parent(left),
+                };
+                if !ct_eq(&parent, node) {
+                    return Err(MerkleError::NodeMismatch { level, index });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Estimates the heap bytes held by the levels: every level's capacity in hashes,
+    /// plus the outer Vec of levels.  The upper levels add roughly as much as the leaves.
+    pub fn memory_usage(&self) -> usize {
+        let hashes: usize = self.levels.iter().map(|level| level.capacity() * N).sum();
+        hashes + self.levels.capacity() * size_of::<Vec<Hash<N>>>()
+    }
+
+}
+
+/*
+    Tree shape helpers
+
+    The shape of the tree depends only on its leaf count n:
+        - level k holds ceil(n / 2^k) nodes
+        - node (k, j) covers leaves [j * 2^k, (j + 1) * 2^k)
+        - there are ceil(log2 n) + 1 levels
+*/
+
+/// Number of levels in a tree with `size` leaves (0 for an empty tree).
+pub(crate) fn height_for(size: usize) -> usize {
+    if size == 0 {
+        return 0;
+    }
+    (usize::BITS - (size - 1).leading_zeros()) as usize + 1
+}
+
+/// Number of nodes at `level` in a tree with `size` leaves.
+pub(crate) fn level_width(size: usize, level: usize) -> usize {
+    if size == 0 {
+        return 0;
+    }
+    ((size - 1).checked_shr(level as u32).unwrap_or(0)) + 1
+}
+
+/// Computes the root of a SHA-256 tree over `leaves` with duplicated odd nodes, the root
+/// `MerkleTree::from_leaves` would have, without keeping any level but the current one.
+/// None for no leaves.
+pub fn root_of(leaves: &[Hash]) -> Option<Hash> {
+    root_of_with::<Sha256, 32>(OddNodePolicy::Duplicate, leaves)
+}
+
+/// Same as `root_of`, for a tree hashed with the digest `D` using `policy`.
+pub fn root_of_with<D: Digest, const N: usize>(
+    policy: OddNodePolicy,
+    leaves: &[Hash<N>],
+) -> Option<Hash<N>> {
+    if leaves.is_empty() {
+        return None;
+    }
+
+    // A single working buffer, each level overwrites the front half of the one below
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        let width = level.len().div_ceil(2);
+        for i in 0..width {
+            level[i] = match level.get(2 * i + 1) {
+                Some(right) => hash_internal_with::<D, N>(level[2 * i], *right),
+                None => policy.lone_parent::<D, N>(&level[2 * i]),
+            };
+        }
+        level.truncate(width);
+    }
+
+    Some(level[0])
+}
+
+/// True if `levels` has exactly the level count and widths its leaf count implies,
+/// or is one of the two empty forms.  Every indexed lookup into the levels relies on this.
+pub(crate) fn has_valid_shape<const N: usize>(levels: &[Vec<Hash<N>>]) -> bool {
+    let size = levels.first().map_or(0, |leaves| leaves.len());
+    if size == 0 {
+        return levels.len() <= 1;
+    }
+
+    levels.len() == height_for(size)
+        && levels
+            .iter()
+            .enumerate()
+            .all(|(level, nodes)| nodes.len() == level_width(size, level))
+}
+
+impl<D: Digest, const N: usize> Default for MerkleTree<D, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Collects all keys first, then builds the tree in a single bottom-up pass.
+impl<D: Digest, const N: usize> FromIterator<Key> for MerkleTree<D, N> {
+    fn from_iter<I: IntoIterator<Item = Key>>(iter: I) -> Self {
+        let keys: Vec<Key> = iter.into_iter().collect();
+        MerkleTree::from_keys(&keys)
+    }
+}
+
+/// Hashes every key, then rebuilds the upper levels once, like `append_many`.
+impl<D: Digest, const N: usize> Extend<Key> for MerkleTree<D, N> {
+    fn extend<I: IntoIterator<Item = Key>>(&mut self, iter: I) {
+        if self.levels.is_empty() {
+            self.levels.push(Vec::new());
+        }
+
+        // Hash straight into the leaf level, no temporary Vec of leaves
+        let before = self.levels[0].len();
+        for key in iter {
+            let leaf = self.key_leaf_cached(&key);
+            self.levels[0].push(leaf);
+        }
+
+        if self.levels[0].len() > before {
+            self.rebuild();
+        }
+    }
+}
+
+// Implemented by hand so that D itself doesn't need Clone, PartialEq or Debug.
+impl<D: Digest, const N: usize> Clone for MerkleTree<D, N> {
+    fn clone(&self) -> Self {
+        MerkleTree {
+            levels: self.levels.clone(),
+            policy: self.policy,
+            endianness: self.endianness,
+            domain: self.domain.clone(),
+            hasher: self.hasher.clone(),
+            #[cfg(feature = "std")]
+            key_cache: self.key_cache.clone(),
+            #[cfg(feature = "metrics")]
+            rebuilds: self.rebuilds,
+            root_cache: self.root_cache.clone(),
+            digest: PhantomData,
+        }
+    }
+}
+
+impl<D: Digest, const N: usize> PartialEq for MerkleTree<D, N> {
+    fn eq(&self, other: &Self) -> bool {
+        // Compare through iter_levels so an emptied tree equals a new one
+        self.iter_levels().eq(other.iter_levels())
+            && self.policy == other.policy
+            && self.endianness == other.endianness
+            && self.domain == other.domain
+    }
+}
+
+impl<D: Digest, const N: usize> Eq for MerkleTree<D, N> {}
+
+/// Prints a one-line summary: `MerkleTree(root=<hex>, leaves=<n>)`, or `root=<empty>`.
+impl<D: Digest, const N: usize> fmt::Display for MerkleTree<D, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let root = self.root_hex().unwrap_or_else(|| String::from("<empty>"));
+        write!(f, "MerkleTree(root={}, leaves={})", root, self.len())
+    }
+}
+
+/// Prints each level as a list of hex hashes, leaves first.
+impl<D: Digest, const N: usize> fmt::Debug for MerkleTree<D, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex_levels: Vec<Vec<String>> = self
+            .iter_levels()
+            .map(|level| level.iter().map(|hash| hash_to_hex(hash)).collect())
+            .collect();
+
+        f.debug_struct("MerkleTree")
+            .field("levels", &hex_levels)
+            .field("policy", &self.policy)
+            .field("endianness", &self.endianness)
+            .field("domain", &hash_to_hex(&self.domain))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashing::hash_key;
+    use crate::proof::verify_proof_with;
+    use proptest::prelude::*;
+
+    /// One call into the public API, with indices that are often out of range.
+    #[derive(Debug, Clone)]
+    enum Op {
+        Append(Key),
+        AppendMany(Vec<Key>),
+        Truncate(usize),
+        Pop,
+        Clear,
+        Proof(usize),
+        Node(usize, usize),
+        RootAt(usize),
+        Leaf(usize),
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            4 => any::<Key>().prop_map(Op::Append),
+            2 => prop::collection::vec(any::<Key>(), 0..20).prop_map(Op::AppendMany),
+            1 => (0..40usize).prop_map(Op::Truncate),
+            1 => Just(Op::Pop),
+            1 => Just(Op::Clear),
+            2 => (0..40usize).prop_map(Op::Proof),
+            2 => (0..8usize, 0..40usize).prop_map(|(level, index)| Op::Node(level, index)),
+            2 => (0..40usize).prop_map(Op::RootAt),
+            2 => (0..40usize).prop_map(Op::Leaf),
+        ]
+    }
+
+    fn policy() -> impl Strategy<Value = OddNodePolicy> {
+        prop_oneof![Just(OddNodePolicy::Duplicate), Just(OddNodePolicy::Promote)]
+    }
+
+    /// Checks a tree grown by `append` against a single full rebuild over the same leaves.
+    fn check_against_rebuild(policy: OddNodePolicy, keys: &[Key]) {
+        let mut appended = Sha256Tree::with_policy(policy);
+        for &key in keys {
+            appended.append(key);
+        }
+
+        let leaves: Vec<Hash> = keys.iter().map(|&key| hash_key(key)).collect();
+        let mut rebuilt = Sha256Tree::with_policy(policy);
+        rebuilt.append_hashes(leaves.iter().copied());
+
+        let same_levels = appended.iter_levels().eq(rebuilt.iter_levels());
+        assert!(same_levels, "{:?} with {} keys", policy, keys.len());
+        assert_eq!(appended.root(), root_of_with::<Sha256, 32>(policy, &leaves));
+        if policy == OddNodePolicy::Duplicate {
+            assert_eq!(appended, Sha256Tree::from_leaves(leaves));
+        }
+    }
+
+    #[test]
+    fn append_matches_full_rebuild_for_every_size() {
+        let keys: Vec<Key> = (0..130).collect();
+        for policy in [OddNodePolicy::Duplicate, OddNodePolicy::Promote] {
+            for size in 0..=keys.len() {
+                check_against_rebuild(policy, &keys[..size]);
+            }
+        }
+    }
+
+    /// The leaves of the RFC 6962 test data used by Certificate Transparency implementations.
+    const CT_LEAVES: [&[u8]; 8] = [
+        b"",
+        b"\x00",
+        b"\x10",
+        b"\x20\x21",
+        b"\x30\x31",
+        b"\x40\x41\x42\x43",
+        b"\x50\x51\x52\x53\x54\x55\x56\x57",
+        b"\x60\x61\x62\x63\x64\x65\x66\x67\x68\x69\x6a\x6b\x6c\x6d\x6e\x6f",
+    ];
+
+    /// The published root after each of CT_LEAVES is appended.
+    const CT_ROOTS: [&str; 8] = [
+        "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+        "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+        "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+        "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+        "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+        "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+        "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+        "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+    ];
+
+    #[test]
+    fn rfc6962_matches_ct_test_vectors() {
+        let mut tree = Sha256Tree::rfc6962();
+        for (leaf, root) in CT_LEAVES.iter().zip(CT_ROOTS) {
+            tree.append_bytes(leaf);
+            assert_eq!(tree.root_hex().as_deref(), Some(root), "after {} leaves", tree.len());
+        }
+    }
+
+    #[test]
+    fn append_takes_unsuffixed_u64_literals() {
+        let mut tree = Sha256Tree::new();
+        tree.append(5);
+        tree.append_key(5u64);
+        tree.append_key(5u32);
+
+        assert_eq!(tree.leaf(0), Some(hash_key(5)));
+        assert_eq!(tree.leaf(1), tree.leaf(0));
+        assert_ne!(tree.leaf(2), tree.leaf(0));
+    }
+
+    #[test]
+    fn contains_finds_appended_keys_only() {
+        let mut tree = Sha256Tree::new();
+        assert!(!tree.contains(7u64));
+        assert_eq!(tree.index_of(7u64), None);
+
+        tree.append(7u64);
+        tree.append(42u64);
+        assert!(tree.contains(7u64));
+        assert!(tree.contains(42u64));
+        assert!(!tree.contains(8u64));
+        assert_eq!(tree.index_of(42u64), Some(1));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn recurring_keys_hit_the_cache() {
+        use crate::hashing::hash_leaf_bytes;
+        use core::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        // A leaf function that counts how often the tree actually hashes
+        let hashed = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&hashed);
+        let mut tree = Sha256Tree::new_cached();
+        tree.hasher = Some(Hasher::new(
+            move |data| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                hash_leaf_bytes(data)
+            },
+            hash_internal_with::<Sha256, 32>,
+        ));
+        let keys: Vec<Key> = (0..8).collect();
+
+        // Only the first round hashes, every later one is served from the cache
+        for _ in 0..100 {
+            tree.append_many(&keys);
+            for &key in &keys {
+                tree.append(key);
+            }
+        }
+
+        assert_eq!(hashed.load(Ordering::Relaxed), keys.len());
+        assert_eq!(tree.cached_keys(), keys.len());
+        assert_eq!(tree.len(), 100 * 2 * keys.len());
+
+        // The cached leaves are the ones an uncached tree computes
+        let mut plain = Sha256Tree::new();
+        for _ in 0..100 {
+            plain.append_many(&keys);
+            plain.append_many(&keys);
+        }
+        assert_eq!(tree.root(), plain.root());
+    }
+
+    /// A mutation of the tree, for checking the cached root goes stale after each kind.
+    #[derive(Debug, Clone)]
+    enum Mutation {
+        Append(Key),
+        AppendMany(Vec<Key>),
+        AppendHashes(Vec<Key>),
+        Update(usize, Key),
+        Truncate(usize),
+        Pop,
+        Clear,
+    }
+
+    fn mutation() -> impl Strategy<Value = Mutation> {
+        prop_oneof![
+            4 => any::<Key>().prop_map(Mutation::Append),
+            2 => prop::collection::vec(any::<Key>(), 0..10).prop_map(Mutation::AppendMany),
+            2 => prop::collection::vec(any::<Key>(), 0..10).prop_map(Mutation::AppendHashes),
+            3 => (0..30usize, any::<Key>()).prop_map(|(index, key)| Mutation::Update(index, key)),
+            1 => (0..30usize).prop_map(Mutation::Truncate),
+            1 => Just(Mutation::Pop),
+            1 => Just(Mutation::Clear),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn cached_root_follows_every_mutation(
+            policy in policy(),
+            mutations in prop::collection::vec(mutation(), 0..60),
+        ) {
+            let mut tree = Sha256Tree::with_policy(policy);
+            let mut leaves: Vec<Hash> = Vec::new();
+
+            for mutation in mutations {
+                // Fill the cache before every mutation
+                let before = tree.root();
+                prop_assert_eq!(before, tree.root());
+
+                match mutation {
+                    Mutation::Append(key) => {
+                        tree.append(key);
+                        leaves.push(hash_key(key));
+                    }
+                    Mutation::AppendMany(keys) => {
+                        tree.append_many(&keys);
+                        leaves.extend(keys.iter().map(|&key| hash_key(key)));
+                    }
+                    Mutation::AppendHashes(keys) => {
+                        let hashes: Vec<Hash> = keys.iter().map(|&key| hash_key(key)).collect();
+                        tree.append_hashes(hashes.iter().copied());
+                        leaves.extend(hashes);
+                    }
+                    Mutation::Update(index, key) => {
+                        let mut mutable = mem::take(&mut tree).into_mutable();
+                        if mutable.update(index, key).is_ok() {
+                            leaves[index] = hash_key(key);
+                        }
+                        tree = mutable.into_tree();
+                    }
+                    Mutation::Truncate(len) => {
+                        tree.truncate(len);
+                        leaves.truncate(len);
+                    }
+                    Mutation::Pop => {
+                        tree.pop();
+                        leaves.pop();
+                    }
+                    Mutation::Clear => {
+                        tree.clear();
+                        leaves.clear();
+                    }
+                }
+
+                prop_assert_eq!(tree.root(), root_of_with::<Sha256, 32>(policy, &leaves));
+            }
+        }
+
+        #[test]
+        fn append_matches_full_rebuild(
+            policy in policy(),
+            keys in prop::collection::vec(any::<Key>(), 0..300),
+        ) {
+            check_against_rebuild(policy, &keys);
+        }
+
+        #[test]
+        fn random_operations_never_panic(
+            policy in policy(),
+            ops in prop::collection::vec(op(), 0..60),
+        ) {
+            let mut tree = Sha256Tree::with_policy(policy);
+            let mut keys: Vec<Key> = Vec::new();
+
+            for op in ops {
+                match op {
+                    Op::Append(key) => {
+                        tree.append(key);
+                        keys.push(key);
+                    }
+                    Op::AppendMany(batch) => {
+                        tree.append_many(&batch);
+                        keys.extend_from_slice(&batch);
+                    }
+                    Op::Truncate(len) => {
+                        tree.truncate(len);
+                        keys.truncate(len);
+                    }
+                    Op::Pop => {
+                        let popped = tree.pop();
+                        prop_assert_eq!(popped, keys.pop().map(hash_key));
+                    }
+                    Op::Clear => {
+                        tree.clear();
+                        keys.clear();
+                    }
+                    Op::Proof(index) => match tree.proof(index) {
+                        Some(proof) => {
+                            let root = tree.root().unwrap();
+                            let leaf = hash_key(keys[index]);
+                            prop_assert!(verify_proof_with::<Sha256, 32>(leaf, &proof, root));
+                        }
+                        None => prop_assert!(index >= keys.len()),
+                    },
+                    Op::Node(level, index) => {
+                        let in_range =
+                            level < tree.height() && index < level_width(keys.len(), level);
+                        prop_assert_eq!(tree.node(level, index).is_some(), in_range);
+                    }
+                    Op::RootAt(size) => {
+                        let leaves: Vec<Hash> =
+                            keys.iter().take(size).map(|&key| hash_key(key)).collect();
+                        let expected = if size == 0 || size > keys.len() {
+                            None
+                        } else {
+                            root_of_with::<Sha256, 32>(policy, &leaves)
+                        };
+                        prop_assert_eq!(tree.root_at(size), expected);
+                    }
+                    Op::Leaf(index) => {
+                        let expected = keys.get(index).map(|&key| hash_key(key));
+                        prop_assert_eq!(tree.leaf(index), expected);
+                    }
+                }
+
+                prop_assert_eq!(tree.len(), keys.len());
+                prop_assert!(tree.validate().is_ok());
+            }
+        }
+    }
+}
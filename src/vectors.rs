@@ -0,0 +1,98 @@
+/*
+    Test vectors
+
+    Fixed inputs and the roots this crate produces for them, so another implementation
+    (or a later version of this one) can check it hashes exactly the same way.
+
+    Every vector uses SHA-256, keys hashed as H(0x00 || 8 big-endian bytes) and
+    nodes as H(0x01 || left || right).  The roots were computed independently of
+    this crate and are pinned here as hex.
+
+    golden_roots generates rather than pins: the root of every default tree over keys 0..n,
+    for pinning in a downstream CI.  One tree is grown a key at a time, so each size is
+    read off after a single O(log n) append and every odd/even width gets covered.
+*/
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::hashing::{Hash, Key, hash_key};
+use crate::tree::{OddNodePolicy, Sha256Tree};
+
+/// A fixed list of keys and the root a SHA-256 tree with `policy` must have after appending them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestVector {
+    /// Keys appended in order.
+    pub keys: &'static [Key],
+    /// How lone nodes on odd-length levels are handled.
+    pub policy: OddNodePolicy,
+    /// Expected root as lowercase hex, None for the empty tree.
+    pub root: Option<&'static str>,
+}
+
+/// Returns the known-good vectors: the empty tree, a single leaf, [5, 10, 30] and a few more.
+pub fn test_vectors() -> &'static [TestVector] {
+    const VECTORS: &[TestVector] = &[
+        TestVector {
+            keys: &[],
+            policy: OddNodePolicy::Duplicate,
+            root: None,
+        },
+        TestVector {
+            keys: &[5],
+            policy: OddNodePolicy::Duplicate,
+            root: Some("daeef3f9f29f50305ceb2a6cac41452a76db046389ff9f1e278103258de90bb8"),
+        },
+        TestVector {
+            keys: &[5, 10],
+            policy: OddNodePolicy::Duplicate,
+            root: Some("8002bb3e888a111a4148fac8b3301d9bff9b325a72e91941fc4bfe096eb7a826"),
+        },
+        TestVector {
+            keys: &[5, 10, 30],
+            policy: OddNodePolicy::Duplicate,
+            root: Some("bfb62b23335bbfd96487bfb95bcf58ebede919a1e7e7a82dc937d6c6c53fbb06"),
+        },
+        TestVector {
+            keys: &[5, 10, 30],
+            policy: OddNodePolicy::Promote,
+            root: Some("17c83db7d722e9497fd7828630e27d3494de4f48159c0fbfe148e52c04a7089f"),
+        },
+        TestVector {
+            keys: &[0, 1, 2, 3, 4, 5, 6],
+            policy: OddNodePolicy::Duplicate,
+            root: Some("681435cc2d679eab4af7c993838b279e7f3ad074e397f181c14fb1003050beb8"),
+        },
+        TestVector {
+            keys: &[0, 1, 2, 3, 4, 5, 6],
+            policy: OddNodePolicy::Promote,
+            root: Some("45cea7edca9543ee5575a5774d0d8fa9321a8be084b3fb657fa4f6d071a3c94c"),
+        },
+        TestVector {
+            keys: &[0, 1, 2, 3, 4, 5, 6, 7],
+            policy: OddNodePolicy::Duplicate,
+            root: Some("b15acd8b1ccf7a9b81c04f69b27e5cabd67e90be0e6ff6a4d1ed87004a4f0cc1"),
+        },
+    ];
+
+    VECTORS
+}
+
+/// Hashes each key into its SHA-256 leaf, the way `append` does.
+pub fn leaves_from_keys(keys: &[Key]) -> Vec<Hash> {
+    keys.iter().map(|&key| hash_key(key)).collect()
+}
+
+/// Returns (n, root as lowercase hex) for the default SHA-256 tree over keys `0..n`,
+/// for every n in 1..=max_n.
+pub fn golden_roots(max_n: usize) -> Vec<(usize, String)> {
+    let mut tree = Sha256Tree::new();
+    let mut roots = Vec::with_capacity(max_n);
+
+    for n in 1..=max_n {
+        tree.append((n - 1) as Key);
+        roots.push((n, tree.root_hex().expect("tree has at least one leaf")));
+    }
+
+    roots
+}
@@ -0,0 +1,96 @@
+// Counts heap allocations made by the current thread, to check that rebuilds reuse
+// the level buffers instead of allocating new ones.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use merkle_tree::{Hash, Sha256Tree, hash_key};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Number of allocations `f` makes on this thread.
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+const SIZE: u64 = 1000;
+const BATCH: u64 = 100;
+
+/// A tree of SIZE leaves whose level buffers have all been through one truncate/regrow cycle.
+fn warmed_tree() -> Sha256Tree {
+    let mut tree = Sha256Tree::new();
+    tree.append_many(&(0..SIZE).collect::<Vec<_>>());
+    tree.truncate((SIZE - BATCH) as usize);
+    tree.append_many(&(SIZE - BATCH..SIZE).collect::<Vec<_>>());
+    tree
+}
+
+#[test]
+fn append_hashes_rebuilds_without_allocating() {
+    let mut tree = warmed_tree();
+    let root = tree.root();
+    let batch: Vec<Hash> = (SIZE - BATCH..SIZE).map(hash_key).collect();
+
+    for _ in 0..10 {
+        tree.truncate((SIZE - BATCH) as usize);
+        let count = allocations(|| tree.append_hashes(batch.iter().copied()));
+        assert_eq!(count, 0);
+        assert_eq!(tree.root(), root);
+    }
+}
+
+#[test]
+fn append_many_allocates_only_key_bytes() {
+    let mut tree = warmed_tree();
+    let root = tree.root();
+    let keys: Vec<u64> = (SIZE - BATCH..SIZE).collect();
+
+    // Each key is encoded into its leaf bytes, nothing else allocates
+    for _ in 0..10 {
+        tree.truncate((SIZE - BATCH) as usize);
+        let count = allocations(|| tree.append_many(&keys));
+        assert_eq!(count, keys.len());
+        assert_eq!(tree.root(), root);
+
+        tree.truncate((SIZE - BATCH) as usize);
+        let count = allocations(|| tree.extend(keys.iter().copied()));
+        assert_eq!(count, keys.len());
+        assert_eq!(tree.root(), root);
+    }
+}
+
+#[test]
+fn append_allocates_only_key_bytes() {
+    let mut tree = warmed_tree();
+    tree.truncate((SIZE - BATCH) as usize);
+
+    // Every level already has room for SIZE leaves, so a key costs only its bytes
+    for key in SIZE - BATCH..SIZE {
+        assert_eq!(allocations(|| tree.append(key)), 1);
+    }
+}
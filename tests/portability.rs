@@ -0,0 +1,97 @@
+// Checks the byte-level promises from the Portability notes in lib.rs: pinned roots
+// for fixed inputs, and proofs whose encoding uses fixed-width big-endian counts.
+
+use merkle_tree::{
+    Proof, RootCommitment, Sha256Tree, golden_roots, hash_key, hash_to_hex, test_vectors,
+    verify_proof,
+};
+
+fn tree_of(keys: &[u64]) -> Sha256Tree {
+    let mut tree = Sha256Tree::new();
+    tree.append_many(keys);
+    tree
+}
+
+#[test]
+fn test_vectors_match() {
+    for vector in test_vectors() {
+        let mut tree = Sha256Tree::with_policy(vector.policy);
+        for &key in vector.keys {
+            tree.append(key);
+        }
+        assert_eq!(tree.root_hex().as_deref(), vector.root, "{:?}", vector);
+    }
+}
+
+#[test]
+fn golden_roots_agree_with_test_vectors() {
+    let golden = golden_roots(8);
+    assert_eq!(
+        golden[6].1,
+        "681435cc2d679eab4af7c993838b279e7f3ad074e397f181c14fb1003050beb8"
+    );
+    assert_eq!(
+        golden[7].1,
+        "b15acd8b1ccf7a9b81c04f69b27e5cabd67e90be0e6ff6a4d1ed87004a4f0cc1"
+    );
+}
+
+#[test]
+fn proof_bytes_round_trip() {
+    let keys: Vec<u64> = (0..7).collect();
+    let tree = tree_of(&keys);
+    let root = tree.root().unwrap();
+
+    for (index, &key) in keys.iter().enumerate() {
+        let proof: Proof = Proof::new(tree.proof(index).unwrap());
+        let bytes = proof.to_bytes();
+
+        // u32 big-endian step count, then 32 hash bytes and 1 direction byte per step
+        assert_eq!(bytes[..4], (proof.depth() as u32).to_be_bytes());
+        assert_eq!(bytes.len(), 4 + proof.depth() * 33);
+        assert_eq!(bytes.len(), proof.byte_len());
+
+        let decoded = Proof::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, proof);
+        assert!(verify_proof(hash_key(key), decoded.steps(), root));
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn proofs_stream_back_to_back() {
+    use merkle_tree::{read_proof, write_proof};
+    use std::io::Cursor;
+
+    let keys: Vec<u64> = (0..7).collect();
+    let tree = tree_of(&keys);
+    let proofs: Vec<Proof> =
+        (0..keys.len()).map(|i| Proof::new(tree.proof(i).unwrap())).collect();
+
+    let mut stream = Vec::new();
+    for proof in &proofs {
+        write_proof(&mut stream, proof).unwrap();
+    }
+
+    let mut reader = Cursor::new(stream);
+    for proof in &proofs {
+        assert_eq!(&read_proof::<_, sha2::Sha256, 32>(&mut reader).unwrap(), proof);
+    }
+    assert!(read_proof::<_, sha2::Sha256, 32>(&mut reader).is_err());
+}
+
+#[test]
+fn commitment_bytes_are_fixed_width() {
+    let tree = tree_of(&[5, 10, 30]);
+    let bytes = tree.commitment().to_bytes();
+
+    // version, u32 hash size, u64 tree size, root
+    assert_eq!(bytes[0], 1);
+    assert_eq!(bytes[1..5], 32u32.to_be_bytes());
+    assert_eq!(bytes[5..13], 3u64.to_be_bytes());
+    assert_eq!(
+        hash_to_hex(&bytes[13..]),
+        "bfb62b23335bbfd96487bfb95bcf58ebede919a1e7e7a82dc937d6c6c53fbb06"
+    );
+    assert_eq!(RootCommitment::from_bytes(&bytes), Ok(tree.commitment()));
+}